@@ -1,12 +1,51 @@
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fmt;
+use std::iter;
 use std::ops;
-use std::time::{Instant};
+use std::time::{Duration, Instant};
 
-#[derive(Debug)]
+#[cfg(test)]
+macro_rules! assert_rational_approx {
+    ($r:expr, $value:expr, $error_bound:expr) => {{
+        let r: Rational = $r;
+        let (value, error_bound): (f64, f64) = ($value, $error_bound);
+        let error = (r.value() - value).abs();
+        assert!(
+            error <= error_bound,
+            "{}/{} = {} is {} away from {}, more than {}",
+            r.numer, r.denom, r.value(), error, value, error_bound
+        );
+    }};
+}
+
+#[derive(Debug, Clone, Copy)]
 struct Rational {
     numer: i64,
     denom: i64,
 }
 
+#[allow(dead_code)]
+#[derive(Debug, PartialEq)]
+enum RationalError {
+    ZeroDenominator,
+    InvalidFormat,
+    Overflow(OverflowDirection),
+    // the reduced denominator did not fit in i64, so the value is too fine-grained to hold
+    DenominatorOverflow,
+    OutOfRange,
+}
+
+// the sign of the exact result that did not fit in i64
+#[allow(dead_code)]
+#[derive(Debug, PartialEq)]
+enum OverflowDirection {
+    Positive,
+    Negative,
+}
+
+// main only reaches the operators and fast_from; the tests below cover the rest
+#[allow(dead_code)]
 impl Rational {
     fn gcd(a: i64, b: i64) -> i64 {
         if b == 0 {
@@ -18,62 +57,291 @@ impl Rational {
     }
 
     fn new(numer: i64, denom: i64) -> Rational {
-        let g = Rational::gcd(numer, denom);
-        Rational {
-            numer: numer / g,
-            denom: denom / g,
+        let mut r = Rational::raw(numer, denom);
+        r.reduce_in_place();
+        r
+    }
+
+    fn raw(numer: i64, denom: i64) -> Rational {
+        Rational { numer, denom }
+    }
+
+    // keeps the fraction unreduced, so invariants_hold may be false; equality and
+    // ordering cross-multiply and still treat it as equal to its reduced form
+    fn new_raw(numer: i64, denom: i64) -> Result<Rational, RationalError> {
+        if denom == 0 {
+            return Err(RationalError::ZeroDenominator);
+        }
+        if denom > 0 {
+            return Ok(Rational::raw(numer, denom));
+        }
+        match (numer.checked_neg(), denom.checked_neg()) {
+            (Some(numer), Some(denom)) => Ok(Rational::raw(numer, denom)),
+            _ => Err(RationalError::Overflow(if numer < 0 {
+                OverflowDirection::Positive
+            } else {
+                OverflowDirection::Negative
+            })),
+        }
+    }
+
+    fn reduce_in_place(&mut self) {
+        let mut g = Rational::gcd(self.numer, self.denom).abs();
+        if self.denom < 0 {
+            g = -g;
+        }
+        self.numer /= g;
+        self.denom /= g;
+    }
+
+    fn gcd_i128(a: i128, b: i128) -> i128 {
+        let (mut a, mut b) = (a.abs(), b.abs());
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        a
+    }
+
+    fn from_i128(numer: i128, denom: i128) -> Option<Rational> {
+        Rational::try_from_i128(numer, denom).ok()
+    }
+
+    // like from_i128, but says which part of the reduced fraction did not fit in i64
+    fn try_from_i128(numer: i128, denom: i128) -> Result<Rational, RationalError> {
+        let mut g = Rational::gcd_i128(numer, denom);
+        if denom < 0 {
+            g = -g;
         }
+        let (numer, denom) = (numer / g, denom / g);
+        let numer = i64::try_from(numer).map_err(|_| {
+            RationalError::Overflow(if numer < 0 { OverflowDirection::Negative } else { OverflowDirection::Positive })
+        })?;
+        let denom = i64::try_from(denom).map_err(|_| RationalError::DenominatorOverflow)?;
+        Ok(Rational::raw(numer, denom))
+    }
+
+    fn normalized(&self) -> Rational {
+        Rational::new(self.numer, self.denom)
+    }
+
+    fn invariants_hold(&self) -> bool {
+        self.denom > 0 && Rational::gcd(self.numer, self.denom).abs() == 1
     }
 
     fn value(&self) -> f64 {
         self.numer as f64 / self.denom as f64 
     }
 
+    // from and fast_from both return the first mediant on the Stern-Brocot descent toward
+    // value whose f64 value lies in [value - error_bound, value + error_bound], which is the
+    // simplest such fraction; fast_from only skips the runs of same-side steps in between
     fn from(value: f64, error_bound: f64) -> Rational {
+        Rational::from_with_stats(value, error_bound).0
+    }
+
+    fn from_with_stats(value: f64, error_bound: f64) -> (Rational, u64) {
+        let mut search = SternBrocotSearch::new(value, error_bound);
+        let mut steps: u64 = 0;
+        loop {
+            steps += 1;
+            if let SearchStatus::Done(result) = search.step() {
+                break (result, steps);
+            }
+        }
+    }
+
+    fn fast_from(value: f64, error_bound: f64) -> Rational {
+        Rational::fast_from_with_stats(value, error_bound).0
+    }
 
+    fn fast_from_with_stats(value: f64, error_bound: f64) -> (Rational, u64) {
+
+        let mut steps: u64 = 0;
         let mut lower_bound = Rational::new(0, 1);
         let mut upper_bound = Rational::new(1, 0);
 
         loop {
+            steps += 1;
             let m: Rational = Rational::new(
                 lower_bound.numer + upper_bound.numer,
                 lower_bound.denom + upper_bound.denom,
             );
             let m_value: f64 = m.value();
             if m_value < value - error_bound {
-                lower_bound = Rational::new(m.numer, m.denom);
+                let c = Rational::parametric_search(true, &m, &upper_bound, value, error_bound);
+                lower_bound = Rational::new(m.numer + (c-1) * upper_bound.numer, m.denom + (c-1) * upper_bound.denom);
             } else if m_value > value + error_bound {
-                upper_bound = Rational::new(m.numer, m.denom);
+                let c = Rational::parametric_search(false, &m, &lower_bound, value, error_bound);
+                upper_bound = Rational::new(m.numer + (c-1) * lower_bound.numer, m.denom + (c-1) * lower_bound.denom);
             } else {
-                break Rational::new(m.numer, m.denom);
+                break (Rational::new(m.numer, m.denom), steps);
             }
         }
     }
 
-    fn fast_from(value: f64, error_bound: f64) -> Rational {
+    // counts the mediants from would visit, but measures each run of same-side steps
+    // m + k * bound with a doubling then bisecting search instead of walking it
+    fn steps_to_reach(value: f64, error_bound: f64) -> u64 {
 
+        let mut steps: u64 = 0;
         let mut lower_bound = Rational::new(0, 1);
         let mut upper_bound = Rational::new(1, 0);
 
         loop {
-            let m: Rational = Rational::new(
-                lower_bound.numer + upper_bound.numer,
-                lower_bound.denom + upper_bound.denom,
-            );
+            steps += 1;
+            let m: Rational = lower_bound.mediant(&upper_bound);
+            let m_value: f64 = m.value();
+            let (toward, below) = if m_value < value - error_bound {
+                (upper_bound, true)
+            } else if m_value > value + error_bound {
+                (lower_bound, false)
+            } else {
+                break steps;
+            };
+            let same_side = |k: i64| {
+                let numer = k.checked_mul(toward.numer).and_then(|n| n.checked_add(m.numer));
+                let denom = k.checked_mul(toward.denom).and_then(|d| d.checked_add(m.denom));
+                numer.zip(denom).is_some_and(|(numer, denom)| {
+                    let k_value = Rational::raw(numer, denom).value();
+                    if below { k_value < value - error_bound } else { k_value > value + error_bound }
+                })
+            };
+            let (mut lo, mut hi): (i64, i64) = (0, 1);
+            while same_side(hi) {
+                lo = hi;
+                hi = hi.saturating_mul(2);
+            }
+            while hi - lo > 1 {
+                let mid = lo + (hi - lo) / 2;
+                if same_side(mid) {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+            steps += lo as u64;
+            let run_end = Rational::raw(m.numer + lo * toward.numer, m.denom + lo * toward.denom);
+            if below {
+                lower_bound = run_end;
+            } else {
+                upper_bound = run_end;
+            }
+        }
+    }
+
+    // from stops at the first in-bound mediant, found at depth d of value's Stern-Brocot
+    // path; this keeps descending the same path down to depth 2d and returns the in-bound
+    // node closest to value, so the result is never farther from value than from's
+    fn from_closest(value: f64, error_bound: f64) -> Rational {
+
+        let mut lower_bound = Rational::new(0, 1);
+        let mut upper_bound = Rational::new(1, 0);
+        let mut closest: Option<(Rational, f64)> = None;
+        let mut depth: u64 = 0;
+        let mut max_depth = u64::MAX;
+
+        while depth < max_depth {
+            depth += 1;
+            let m: Rational = lower_bound.mediant(&upper_bound);
+            let m_value: f64 = m.value();
+            let error = (m_value - value).abs();
+            if error <= error_bound {
+                if closest.is_none() {
+                    max_depth = 2 * depth;
+                }
+                if closest.is_none_or(|(_, closest_error)| error < closest_error) {
+                    closest = Some((m, error));
+                }
+                if error == 0.0 {
+                    break;
+                }
+            }
+            if m_value < value {
+                lower_bound = m;
+            } else {
+                upper_bound = m;
+            }
+        }
+        closest.unwrap().0
+    }
+
+    // every bound the descent moved was out of tolerance when it moved, so only the initial
+    // 0/1 can still pass the check; the result differs from from's only when 0/1 is in bound
+    fn simplest_from(value: f64, error_bound: f64) -> Rational {
+
+        let mut lower_bound = Rational::new(0, 1);
+        let mut upper_bound = Rational::new(1, 0);
+
+        loop {
+            let m: Rational = lower_bound.mediant(&upper_bound);
             let m_value: f64 = m.value();
             if m_value < value - error_bound {
-                let c = Rational::parametric_search(true, &m, &upper_bound, value, error_bound);
-                lower_bound = Rational::new(m.numer + (c-1) * upper_bound.numer, m.denom + (c-1) * upper_bound.denom);
+                lower_bound = m;
             } else if m_value > value + error_bound {
-                let c = Rational::parametric_search(false, &m, &lower_bound, value, error_bound);
-                upper_bound = Rational::new(m.numer + (c-1) * lower_bound.numer, m.denom + (c-1) * lower_bound.denom);
+                upper_bound = m;
             } else {
-                break Rational::new(m.numer, m.denom);
+                // the same test the descent uses, so m itself always passes
+                let in_bound = |r: &Rational| (value - error_bound..=value + error_bound).contains(&r.value());
+                break [lower_bound, upper_bound]
+                    .into_iter()
+                    .filter(in_bound)
+                    .fold(m, |simplest, r| if r.denom < simplest.denom { r } else { simplest });
             }
         }
     }
 
-    
+    fn compare_algorithms(value: f64, error_bound: f64) -> AlgoComparison {
+        let start = Instant::now();
+        let (from_result, from_steps) = Rational::from_with_stats(value, error_bound);
+        let from_time = start.elapsed();
+
+        let start = Instant::now();
+        let (fast_from_result, fast_from_steps) = Rational::fast_from_with_stats(value, error_bound);
+        let fast_from_time = start.elapsed();
+
+        AlgoComparison {
+            from_result,
+            from_steps,
+            from_time,
+            fast_from_result,
+            fast_from_steps,
+            fast_from_time,
+            identical: from_result == fast_from_result,
+        }
+    }
+
+    // a fraction is preferred when "n/d" is no longer than the shortest decimal for value
+    fn prefers_fraction(value: f64, error_bound: f64) -> bool {
+        if value == 0.0 {
+            return false;
+        }
+        let fraction = Rational::fast_from(value.abs(), error_bound).to_compact();
+        fraction.len() <= format!("{}", value.abs()).len()
+    }
+
+    fn from_significant_digits(value: f64, digits: u32) -> Rational {
+        Rational::from_relative_error(value, 10f64.powi(-(digits as i32)))
+    }
+
+    // the descent only covers positive values, so zero is returned directly and negative
+    // values are mirrored; fast_from keeps large magnitudes from walking billions of steps
+    fn from_relative_error(value: f64, rel_error: f64) -> Rational {
+        if value == 0.0 {
+            return Rational::new(0, 1);
+        }
+        let simplest = Rational::fast_from(value.abs(), value.abs() * rel_error);
+        if value < 0.0 { -simplest } else { simplest }
+    }
+
+    fn assert_algorithms_agree(value: f64, error_bound: f64) {
+        let (slow, fast) = (Rational::from(value, error_bound), Rational::fast_from(value, error_bound));
+        assert!(
+            (slow.numer, slow.denom) == (fast.numer, fast.denom),
+            "from gives {}/{} but fast_from gives {}/{} for {} within {}",
+            slow.numer, slow.denom, fast.numer, fast.denom, value, error_bound
+        );
+    }
+
     fn parametric_search(direction: bool, start: &Rational, end: &Rational, value: f64, error_bound: f64) -> i64 {
         let start_numer = start.numer;
         let start_denom = start.denom;
@@ -85,104 +353,3787 @@ impl Rational {
         } else { 
             (end_denom, start_denom) 
         };
+        // start lies strictly beyond the error bound, on the side given by direction; find the
+        // smallest c whose point start + c * end no longer does, which is exactly where the
+        // single mediant steps of from would first leave that side
         let mut lower_bound: i64 = 1;
         let mut upper_bound: i64 = (i64::MAX - start_bound) / end_bound;
-        loop {
-            let mid: i64 = (lower_bound + upper_bound) / 2;
+        while lower_bound < upper_bound {
+            let mid: i64 = lower_bound + (upper_bound - lower_bound) / 2;
             let mid_value: f64 = Rational::new(start_numer + mid * end_numer, start_denom + mid * end_denom).value();
-            
-            if mid == upper_bound {
-                break upper_bound;
-            }
-            if !direction && mid_value < value - error_bound {
-                upper_bound = mid;
-            } else if !direction && mid_value > value + error_bound {
-                lower_bound = mid + 1;
-            } else if direction && mid_value > value + error_bound {
+            let left_start_side = if direction {
+                mid_value >= value - error_bound
+            } else {
+                mid_value <= value + error_bound
+            };
+            if left_start_side {
                 upper_bound = mid;
-            } else if direction && mid_value < value - error_bound {
+            } else {
                 lower_bound = mid + 1;
             }
-            else {
-                break mid;
+        }
+        lower_bound
+    }
+
+    fn convergents_up_to_denominator(value: f64, max_denom: i64) -> Vec<Rational> {
+        let mut convergents: Vec<Rational> = Vec::new();
+        let mut lower_bound = Rational::new(0, 1);
+        let mut upper_bound = Rational::new(1, 0);
+        // the last bound moved, and whether it was the lower one
+        let mut last: Option<(Rational, bool)> = None;
+
+        loop {
+            let m: Rational = Rational::new(
+                lower_bound.numer + upper_bound.numer,
+                lower_bound.denom + upper_bound.denom,
+            );
+            let m_value: f64 = m.value();
+            let below = m_value < value;
+            // a bound is a convergent exactly when the descent turns after it
+            let turned = match last {
+                Some((_, was_below)) => m_value != value && was_below != below,
+                None => false,
+            };
+            if turned {
+                convergents.push(last.unwrap().0);
+            }
+            if m.denom > max_denom {
+                break convergents;
             }
+            if m_value == value {
+                convergents.push(m);
+                break convergents;
+            }
+            if below {
+                lower_bound = m;
+            } else {
+                upper_bound = m;
+            }
+            last = Some((m, below));
         }
     }
-}
 
-impl ops::Add<Rational> for Rational {
-    type Output = Rational;
+    fn as_f64_bounds(&self) -> (f64, f64) {
+        let x = self.value();
+        match self.cmp_f64(x) {
+            Ordering::Less => (x.next_down(), x),
+            Ordering::Equal => (x, x),
+            Ordering::Greater => (x, x.next_up()),
+        }
+    }
 
-    fn add(self, rhs: Rational) -> Rational {
-        Rational::new(
-            self.numer * rhs.denom + self.denom * rhs.numer,
-            self.denom * rhs.denom,
-        )
+    // exact comparison against a finite float; |mantissa * denom| < 2^116, so once a
+    // shifted side would need more than 126 bits the sign of the other side decides it
+    fn cmp_f64(&self, x: f64) -> Ordering {
+        let (mantissa, exponent) = Rational::decompose_f64(x);
+        let (numer, scaled) = (self.numer as i128, mantissa * self.denom as i128);
+        let bits = |v: i128| 128 - v.unsigned_abs().leading_zeros();
+        let shift = exponent.unsigned_abs();
+        if exponent < 0 {
+            if numer == 0 || bits(numer) + shift > 126 {
+                return numer.cmp(&0).then(0.cmp(&scaled));
+            }
+            (numer << shift).cmp(&scaled)
+        } else {
+            if scaled == 0 || bits(scaled) + shift > 126 {
+                return 0.cmp(&scaled).then(numer.cmp(&0));
+            }
+            numer.cmp(&(scaled << shift))
+        }
     }
-}
 
-impl ops::Sub<Rational> for Rational {
-    type Output = Rational;
+    // x == mantissa * 2^exponent for finite x
+    fn decompose_f64(x: f64) -> (i128, i32) {
+        let bits = x.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i32;
+        let fraction = (bits & ((1 << 52) - 1)) as i128;
+        let (mantissa, exponent) = if exponent == 0 {
+            (fraction, -1074)
+        } else {
+            (fraction | (1 << 52), exponent - 1075)
+        };
+        (if x < 0.0 { -mantissa } else { mantissa }, exponent)
+    }
 
-    fn sub(self, rhs: Rational) -> Rational {
-        Rational::new(
-            self.numer * rhs.denom - self.denom * rhs.numer,
-            self.denom * rhs.denom,
-        )
+    fn from_f64_exact(x: f64) -> Option<Rational> {
+        if !x.is_finite() {
+            return None;
+        }
+        let (mantissa, exponent) = Rational::decompose_f64(x);
+        if mantissa == 0 {
+            return Some(Rational::new(0, 1));
+        }
+        let shift = mantissa.trailing_zeros() as i32;
+        let (mantissa, exponent) = (mantissa >> shift, exponent + shift);
+        if exponent >= 0 {
+            Rational::from_i128(mantissa.checked_mul(1i128.checked_shl(exponent as u32)?)?, 1)
+        } else {
+            Rational::from_i128(mantissa, 1i128.checked_shl(-exponent as u32).filter(|d| *d > 0)?)
+        }
     }
-}
 
-impl ops::Mul<Rational> for Rational {
-    type Output = Rational;
+    // Python's Fraction.limit_denominator, choosing between the best convergent
+    // and the best semiconvergent with denominator <= max_denom
+    fn limit_denominator(&self, max_denom: i64) -> Rational {
+        assert!(max_denom >= 1, "max_denom must be at least 1");
+        if self.denom <= max_denom {
+            return *self;
+        }
+        let (mut p0, mut q0, mut p1, mut q1) = (0, 1, 1, 0);
+        let (mut numer, mut denom) = (self.numer, self.denom);
+        loop {
+            let a = numer.div_euclid(denom);
+            let q2 = q0 as i128 + a as i128 * q1 as i128;
+            if q2 > max_denom as i128 {
+                break;
+            }
+            (p0, q0, p1, q1) = (p1, q1, p0 + a * p1, q2 as i64);
+            (numer, denom) = (denom, numer - a * denom);
+        }
+        let k = (max_denom - q0) / q1;
+        let semiconvergent = Rational::new(p0 + k * p1, q0 + k * q1);
+        let convergent = Rational::new(p1, q1);
+        // both distances share self.denom, so compare |cross difference| / denom exactly in i128
+        let convergent_error = convergent.compare_detailed(self).1.abs() * semiconvergent.denom as i128;
+        let semiconvergent_error = semiconvergent.compare_detailed(self).1.abs() * convergent.denom as i128;
+        if convergent_error <= semiconvergent_error {
+            convergent
+        } else {
+            semiconvergent
+        }
+    }
 
-    fn mul(self, rhs: Rational) -> Rational {
-        Rational::new(self.numer * rhs.numer, self.denom * rhs.denom)
+    fn to_mixed_string(self) -> String {
+        self.into_mixed().to_string()
     }
-}
 
-impl ops::Div<Rational> for Rational {
-    type Output = Rational;
+    fn into_mixed(self) -> MixedNumber {
+        MixedNumber {
+            whole: self.numer / self.denom,
+            fraction: Rational::new(self.numer % self.denom, self.denom),
+        }
+    }
 
-    fn div(self, rhs: Rational) -> Rational {
-        Rational::new(self.numer * rhs.denom, self.denom * rhs.numer)
+    fn from_mixed_str(s: &str) -> Result<Rational, RationalError> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        match parts[..] {
+            [single] => Rational::parse_fraction(single),
+            [whole, fraction] => {
+                let negative = whole.starts_with('-');
+                let whole: i64 = whole.parse().map_err(|_| RationalError::InvalidFormat)?;
+                if !fraction.contains('/') || fraction.contains('-') {
+                    return Err(RationalError::InvalidFormat);
+                }
+                let fraction = Rational::parse_fraction(fraction)?;
+                let (numer, denom) = (fraction.numer as i128, fraction.denom as i128);
+                let magnitude = (whole as i128).abs() * denom + numer;
+                Rational::try_from_i128(if negative { -magnitude } else { magnitude }, denom)
+            }
+            _ => Err(RationalError::InvalidFormat),
+        }
     }
-}
 
-impl ops::Neg for Rational {
-    type Output = Self;
+    fn to_compact(self) -> String {
+        if self.denom == 1 {
+            format!("{}", self.numer)
+        } else {
+            format!("{}/{}", self.numer, self.denom)
+        }
+    }
 
-    fn neg(self) -> Self::Output {
-        Rational::new(-self.numer, self.denom)
+    fn from_compact(s: &str) -> Result<Rational, RationalError> {
+        Rational::parse_fraction(s)
     }
-}
 
-fn main() {
-    println!("{:?}", Rational::new(2, 5) / Rational::new(1, 2));
-    println!("{:?}", Rational::new(2, 7) * Rational::new(1, 2));
-    println!("{:?}", Rational::new(2, 3) + Rational::new(1, 2));
-    println!("{:?}", Rational::new(8, 5) - Rational::new(1, 2));
-    println!("{:?}\n", -Rational::new(2, 3));
+    fn from_unicode_str(s: &str) -> Result<Rational, RationalError> {
+        let s = s.trim();
+        let glyph = match s.chars().last() {
+            Some(c) => c,
+            None => return Err(RationalError::InvalidFormat),
+        };
+        let (numer, denom) = match glyph {
+            '½' => (1, 2),
+            '↉' => (0, 3),
+            '⅓' => (1, 3),
+            '⅔' => (2, 3),
+            '¼' => (1, 4),
+            '¾' => (3, 4),
+            '⅕' => (1, 5),
+            '⅖' => (2, 5),
+            '⅗' => (3, 5),
+            '⅘' => (4, 5),
+            '⅙' => (1, 6),
+            '⅚' => (5, 6),
+            '⅐' => (1, 7),
+            '⅛' => (1, 8),
+            '⅜' => (3, 8),
+            '⅝' => (5, 8),
+            '⅞' => (7, 8),
+            '⅑' => (1, 9),
+            '⅒' => (1, 10),
+            _ => return Rational::from_mixed_str(s),
+        };
+        let whole = s[..s.len() - glyph.len_utf8()].trim();
+        let (negative, whole) = match whole.strip_prefix('-') {
+            Some(rest) => (true, rest.trim()),
+            None => (false, whole),
+        };
+        if !whole.chars().all(|c| c.is_ascii_digit()) {
+            return Err(RationalError::InvalidFormat);
+        }
+        let whole: i64 = if whole.is_empty() {
+            0
+        } else {
+            whole.parse().map_err(|_| RationalError::InvalidFormat)?
+        };
+        let magnitude = whole as i128 * denom + numer;
+        Rational::try_from_i128(if negative { -magnitude } else { magnitude }, denom)
+    }
 
+    fn to_le_bytes(self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&self.numer.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.denom.to_le_bytes());
+        bytes
+    }
 
-    measure_time(6.4285714285, 0.000000001);
-    measure_time(6.4285714285, 0.000000001);
+    fn from_le_bytes(bytes: [u8; 16]) -> Result<Rational, RationalError> {
+        let numer = i64::from_le_bytes(bytes[..8].try_into().unwrap());
+        let denom = i64::from_le_bytes(bytes[8..].try_into().unwrap());
+        let mut r = Rational::new_raw(numer, denom)?;
+        r.reduce_in_place();
+        Ok(r)
+    }
 
-    measure_time(12_581_890_123.5384615384, 0.0000001);
-    measure_time(12_581_890_123.5384615384, 0.0000001);
+    fn parse_fraction(s: &str) -> Result<Rational, RationalError> {
+        let (numer, denom) = match s.split_once('/') {
+            Some((n, d)) => (n, d),
+            None => (s, "1"),
+        };
+        let numer: i64 = numer.parse().map_err(|_| RationalError::InvalidFormat)?;
+        let denom: i64 = denom.parse().map_err(|_| RationalError::InvalidFormat)?;
+        if denom == 0 {
+            return Err(RationalError::ZeroDenominator);
+        }
+        // i128 because negating i64::MIN to normalize the sign does not fit in i64
+        Rational::try_from_i128(numer as i128, denom as i128)
+    }
 
-    measure_time(0.00000000000006 as f64, 0.000000000000001 as f64);
-    measure_time(0.00000000000006 as f64, 0.000000000000001 as f64);
+    fn calkin_wilf(n: usize) -> Vec<Rational> {
+        let mut terms: Vec<Rational> = Vec::with_capacity(n);
+        let mut a = Rational::new(1, 1);
+        for _ in 0..n {
+            terms.push(a);
+            let floor = a.numer / a.denom;
+            a = Rational::new(1, 1) / (Rational::new(2 * floor + 1, 1) - a);
+        }
+        terms
+    }
 
-    measure_time(0.75 as f64, 0.0001 as f64);
-    measure_time(0.75 as f64, 0.0001 as f64);
-}
+    fn approximation_error(value: f64, max_denom: i64) -> Vec<(Rational, f64)> {
+        (1..=max_denom)
+            .map(|denom| {
+                let r = Rational::new((value * denom as f64).round() as i64, denom);
+                (r, r.value() - value)
+            })
+            .collect()
+    }
 
+    fn is_dyadic(&self) -> bool {
+        self.denom & (self.denom - 1) == 0
+    }
 
-fn measure_time(v: f64, err: f64) {
-    let start = Instant::now();
-    println!("{:?}", Rational::fast_from(v, err));
-    let end = Instant::now();
-    println!("{:?}", end.duration_since(start));
-}
+    fn beats(&self, beats_per_measure: i64) -> Rational {
+        *self * Rational::new(beats_per_measure, 1)
+    }
+
+    // a meter is compound when the beat count is a multiple of 3 above 3; since a
+    // Rational is reduced, 6/8 reads as 3/4 and 12/8 as 3/2, so only meters that stay
+    // compound after reducing (9/8, 15/16) are caught
+    fn is_simple_meter(&self) -> bool {
+        let compound = self.numer % 3 == 0 && self.numer > 3;
+        self.numer > 0 && self.is_dyadic() && !compound
+    }
+
+    fn checked_add(&self, other: &Rational) -> Result<Rational, RationalError> {
+        let numer = self.numer as i128 * other.denom as i128 + other.numer as i128 * self.denom as i128;
+        let denom = self.denom as i128 * other.denom as i128;
+        Rational::try_from_i128(numer, denom)
+    }
+
+    fn checked_mul(&self, other: &Rational) -> Result<Rational, RationalError> {
+        let (numer, denom) = self.product_i128(other);
+        Rational::try_from_i128(numer, denom)
+    }
+
+    fn checked_sub(&self, other: &Rational) -> Result<Rational, RationalError> {
+        let numer = self.numer as i128 * other.denom as i128 - other.numer as i128 * self.denom as i128;
+        let denom = self.denom as i128 * other.denom as i128;
+        Rational::try_from_i128(numer, denom)
+    }
+
+    fn to_continued_fraction(self) -> Vec<i64> {
+        let mut terms: Vec<i64> = Vec::new();
+        let (mut numer, mut denom) = (self.numer, self.denom);
+        while denom != 0 {
+            terms.push(numer.div_euclid(denom));
+            (numer, denom) = (denom, numer.rem_euclid(denom));
+        }
+        terms
+    }
+
+    // an empty expansion comes back as the 1/0 sentinel used by from
+    fn from_continued_fraction(terms: &[i64]) -> Rational {
+        let (mut numer, mut prev_numer) = (1, 0);
+        let (mut denom, mut prev_denom) = (0, 1);
+        for &a in terms {
+            (numer, prev_numer) = (a * numer + prev_numer, numer);
+            (denom, prev_denom) = (a * denom + prev_denom, denom);
+        }
+        Rational::new(numer, denom)
+    }
+
+    // the first 33 partial quotients of pi; one more and the convergent overflows i64
+    fn pi_approximation(terms: usize) -> (Rational, f64) {
+        const PI_TERMS: [i64; 33] = [
+            3, 7, 15, 1, 292, 1, 1, 1, 2, 1, 3, 1, 14, 2, 1, 1, 2, 2, 2, 2, 1, 84, 2, 1, 1, 15, 3, 13, 1, 4, 2,
+            6, 6,
+        ];
+        assert!((1..=PI_TERMS.len()).contains(&terms), "terms must be between 1 and {}", PI_TERMS.len());
+        let approximation = Rational::from_continued_fraction(&PI_TERMS[..terms]);
+        (approximation, (approximation.value() - std::f64::consts::PI).abs())
+    }
+
+    // an error's denominator is the convergent's times self's, so when that product
+    // does not fit in i64 the first such failure is returned instead of the list
+    fn convergent_errors(&self) -> Result<Vec<Rational>, RationalError> {
+        let terms = self.to_continued_fraction();
+        (1..=terms.len())
+            .map(|n| Rational::from_continued_fraction(&terms[..n]).checked_sub(self))
+            .collect()
+    }
+
+    // consecutive Fibonacci ratios F(k+1)/F(k); from F(93) on they overflow i64
+    fn golden_ratio_convergents(n: usize) -> Vec<Rational> {
+        assert!(n <= 91, "only the first 91 convergents of the golden ratio fit in i64");
+        iter::successors(Some((1i64, 1i64)), |&(numer, denom)| Some((numer.checked_add(denom)?, numer)))
+            .take(n)
+            .map(|(numer, denom)| Rational::new(numer, denom))
+            .collect()
+    }
+
+    fn continued_fraction_satisfies(&self, predicate: impl Fn(i64) -> bool) -> bool {
+        self.to_continued_fraction().into_iter().all(predicate)
+    }
+
+    // Gosper's algorithm: z = (a*x*y + b*x + c*y + d)/(e*x*y + f*x + g*y + h) starts out
+    // as x + y, takes terms of x and y in turn, and emits a term of z whenever the four
+    // corner ratios a/e, b/f, c/g, d/h share a floor; an exhausted input counts as infinity
+    fn add_via_continued_fraction(&self, other: &Rational) -> Rational {
+        let floor = |n: i128, q: i128| if q < 0 { (-n).div_euclid(-q) } else { n.div_euclid(q) };
+        let mut x_terms = self.to_continued_fraction().into_iter();
+        let mut y_terms = other.to_continued_fraction().into_iter();
+        let (mut x_done, mut y_done, mut take_x) = (false, false, true);
+        let mut state: [i128; 8] = [0, 1, 1, 0, 0, 0, 0, 1];
+        let mut terms: Vec<i64> = Vec::new();
+        loop {
+            let [a, b, c, d, e, f, g, h] = state;
+            let denoms = [e, f, g, h];
+            if denoms == [0; 4] {
+                break;
+            }
+            let r = if x_done && y_done {
+                Some(floor(d, h))
+            } else if denoms.iter().all(|&q| q > 0) || denoms.iter().all(|&q| q < 0) {
+                Some(floor(a, e)).filter(|&r| [floor(b, f), floor(c, g), floor(d, h)] == [r; 3])
+            } else {
+                None
+            };
+            if let Some(r) = r {
+                terms.push(r as i64);
+                state = [e, f, g, h, a - r * e, b - r * f, c - r * g, d - r * h];
+                continue;
+            }
+            if take_x && !x_done || y_done {
+                state = match x_terms.next() {
+                    Some(p) => {
+                        let p = p as i128;
+                        [a * p + c, b * p + d, a, b, e * p + g, f * p + h, e, f]
+                    }
+                    None => {
+                        x_done = true;
+                        [0, 0, a, b, 0, 0, e, f]
+                    }
+                };
+            } else {
+                state = match y_terms.next() {
+                    Some(q) => {
+                        let q = q as i128;
+                        [a * q + b, a, c * q + d, c, e * q + f, e, g * q + h, g]
+                    }
+                    None => {
+                        y_done = true;
+                        [0, a, 0, c, 0, e, 0, g]
+                    }
+                };
+            }
+            take_x = !take_x;
+        }
+        Rational::from_continued_fraction(&terms)
+    }
+
+    fn nth_convergent(&self, n: usize) -> Rational {
+        let terms = self.to_continued_fraction();
+        Rational::from_continued_fraction(&terms[..=n.min(terms.len() - 1)])
+    }
+
+    // every term of a positive fraction is non-negative and they sum to at most
+    // numer + denom, so the total always fits in a u64
+    fn stern_brocot_depth(&self) -> u64 {
+        assert!(self.numer > 0, "only positive fractions are in the Stern-Brocot tree");
+        let terms = self.to_continued_fraction();
+        terms.iter().map(|a| a.unsigned_abs()).sum::<u64>() - 1
+    }
 
+    fn try_pow_rational(&self, exp: &Rational) -> Option<Rational> {
+        let numer = Rational::integer_root(self.numer, exp.denom)?;
+        let denom = Rational::integer_root(self.denom, exp.denom)?;
+        let power = u32::try_from(exp.numer.unsigned_abs()).ok()?;
+        let (numer, denom) = (numer.checked_pow(power)?, denom.checked_pow(power)?);
+        if exp.numer >= 0 {
+            Some(Rational::new(numer, denom))
+        } else if numer != 0 {
+            Some(Rational::new(denom, numer))
+        } else {
+            None
+        }
+    }
 
+    fn integer_root(x: i64, n: i64) -> Option<i64> {
+        if x < 0 {
+            return if n % 2 == 1 { Some(-Rational::integer_root(-x, n)?) } else { None };
+        }
+        if x < 2 {
+            return Some(x);
+        }
+        let n = u32::try_from(n).ok().filter(|&n| (1..64).contains(&n))?;
+        if n == 1 {
+            return Some(x);
+        }
+        // Newton's iteration started above the root decreases to exactly floor(x^(1/n));
+        // the start is at most 2^32, so root^(n - 1) stays within u128
+        let mut root: u128 = 1 << (64 - x.leading_zeros()).div_ceil(n);
+        loop {
+            let next = ((n as u128 - 1) * root + x as u128 / root.pow(n - 1)) / n as u128;
+            if next >= root {
+                break;
+            }
+            root = next;
+        }
+        Some(root as i64).filter(|r| r.checked_pow(n) == Some(x))
+    }
+
+    fn abs_diff(&self, other: &Rational) -> Rational {
+        let numer = self.numer as i128 * other.denom as i128 - other.numer as i128 * self.denom as i128;
+        let denom = self.denom as i128 * other.denom as i128;
+        Rational::from_i128(numer.abs(), denom).expect("difference does not fit in i64")
+    }
+
+    fn mediant(&self, other: &Rational) -> Rational {
+        Rational::new(self.numer + other.numer, self.denom + other.denom)
+    }
+
+    fn weighted_mediant(a: &Rational, b: &Rational, wa: i64, wb: i64) -> Rational {
+        assert!(wa >= 0 && wb >= 0 && (wa, wb) != (0, 0), "weights must be non-negative and not both zero");
+        let numer = wa as i128 * a.numer as i128 + wb as i128 * b.numer as i128;
+        let denom = wa as i128 * a.denom as i128 + wb as i128 * b.denom as i128;
+        Rational::from_i128(numer, denom).expect("weighted mediant does not fit in i64")
+    }
+
+    fn are_farey_neighbors(&self, other: &Rational) -> bool {
+        (self.numer as i128 * other.denom as i128 - self.denom as i128 * other.numer as i128).abs() == 1
+    }
+
+    fn farey_next(&self, max_denom: i64) -> Option<Rational> {
+        if *self == Rational::new(1, 1) {
+            return None;
+        }
+        self.farey_neighbor(max_denom, -1)
+    }
+
+    fn farey_prev(&self, max_denom: i64) -> Option<Rational> {
+        if *self == Rational::new(0, 1) {
+            return None;
+        }
+        self.farey_neighbor(max_denom, 1)
+    }
+
+    // values outside [0, 1] snap to the nearer endpoint; otherwise the closest candidate
+    // from limit_denominator is checked against its neighbor on the far side of self,
+    // and a tie goes to the smaller denominator
+    fn snap_to_farey(&self, order: i64) -> Rational {
+        let clamped = (*self).clamp(Rational::new(0, 1), Rational::new(1, 1));
+        let closest = clamped.limit_denominator(order);
+        let neighbor = if closest <= clamped { closest.farey_next(order) } else { closest.farey_prev(order) };
+        let error = |r: &Rational| r.compare_detailed(&clamped).1.abs();
+        match neighbor {
+            Some(neighbor) => {
+                let closest_error = error(&closest) * neighbor.denom as i128;
+                let neighbor_error = error(&neighbor) * closest.denom as i128;
+                match closest_error.cmp(&neighbor_error) {
+                    Ordering::Less => closest,
+                    Ordering::Greater => neighbor,
+                    Ordering::Equal => if closest.denom <= neighbor.denom { closest } else { neighbor },
+                }
+            }
+            None => closest,
+        }
+    }
+
+    // the neighbor c/d satisfies a*d - b*c = sign, i.e. a*d = sign (mod b), and the
+    // largest such d <= max_denom gives the adjacent term of the sequence
+    fn farey_neighbor(&self, max_denom: i64, sign: i128) -> Option<Rational> {
+        if self.numer < 0 || self.numer > self.denom || self.denom > max_denom {
+            return None;
+        }
+        let (a, b) = (self.numer as i128, self.denom as i128);
+        let residue = (sign * Rational::mod_inverse(a, b)).rem_euclid(b);
+        let d = max_denom as i128 - (max_denom as i128 - residue).rem_euclid(b);
+        Rational::from_i128((a * d - sign) / b, d)
+    }
+
+    // x with a*x == 1 (mod modulus), by the extended Euclidean algorithm; a and modulus
+    // must be coprime, and modulus == 1 gives 0
+    fn mod_inverse(a: i128, modulus: i128) -> i128 {
+        let (mut old_r, mut r) = (a.rem_euclid(modulus), modulus);
+        let (mut old_s, mut s) = (1, 0);
+        while r != 0 {
+            let q = old_r / r;
+            (old_r, r) = (r, old_r - q * r);
+            (old_s, s) = (s, old_s - q * s);
+        }
+        old_s.rem_euclid(modulus)
+    }
+
+    fn farey_mediant(&self, other: &Rational) -> Option<Rational> {
+        if self.are_farey_neighbors(other) {
+            Some(self.mediant(other))
+        } else {
+            None
+        }
+    }
+
+    fn percent_change_to(&self, other: &Rational) -> Rational {
+        assert!(self.numer != 0, "percent change from zero is undefined");
+        (*other - *self) / *self
+    }
+
+    fn sum_exact(values: &[Rational]) -> Option<Rational> {
+        let (numer, denom) = Rational::sum_i128(values)?;
+        Rational::from_i128(numer, denom)
+    }
+
+    // with ratio = p/q the sum is first * (q^n - p^n) / ((q - p) * q^(n - 1)), and
+    // q - p divides q^n - p^n exactly, which keeps the i128 intermediates small; None
+    // rather than a bare Rational because q^n overflows for modest n (1/3 at n = 200)
+    fn geometric_sum(first: &Rational, ratio: &Rational, n: u32) -> Option<Rational> {
+        if n == 0 {
+            return Some(Rational::new(0, 1));
+        }
+        if *ratio == Rational::new(1, 1) {
+            return Rational::from_i128(first.numer as i128 * n as i128, first.denom as i128);
+        }
+        let (p, q) = (ratio.numer as i128, ratio.denom as i128);
+        let terms = (q.checked_pow(n)?.checked_sub(p.checked_pow(n)?)?) / (q - p);
+        let numer = (first.numer as i128).checked_mul(terms)?;
+        let denom = (first.denom as i128).checked_mul(q.checked_pow(n - 1)?)?;
+        Rational::from_i128(numer, denom)
+    }
+
+    fn sum_i128(values: &[Rational]) -> Option<(i128, i128)> {
+        let (mut numer, mut denom): (i128, i128) = (0, 1);
+        for v in values {
+            numer = numer
+                .checked_mul(v.denom as i128)?
+                .checked_add((v.numer as i128).checked_mul(denom)?)?;
+            denom = denom.checked_mul(v.denom as i128)?;
+            let g = Rational::gcd_i128(numer, denom);
+            numer /= g;
+            denom /= g;
+        }
+        Some((numer, denom))
+    }
+
+    fn complement_in_unit(&self) -> Option<Rational> {
+        if self.numer > 0 && self.numer < self.denom {
+            Some(Rational::new(self.denom - self.numer, self.denom))
+        } else {
+            None
+        }
+    }
+
+    fn evaluate_linear(a: &Rational, b: &Rational, xs: &[i64]) -> Vec<Rational> {
+        xs.iter().map(|&x| *a * Rational::new(x, 1) + *b).collect()
+    }
+
+    fn floor(&self) -> i64 {
+        self.numer.div_euclid(self.denom)
+    }
+
+    fn ceil(&self) -> i64 {
+        -(-self.numer).div_euclid(self.denom)
+    }
+
+    // self - floor, done on the remainder so that large magnitudes cannot overflow
+    fn wrap_unit(&self) -> Rational {
+        Rational::new(self.numer.rem_euclid(self.denom), self.denom)
+    }
+
+    fn round_with(&self, mode: RoundingMode) -> i64 {
+        let floor = self.floor();
+        let rest = self.numer.rem_euclid(self.denom);
+        if rest == 0 {
+            return floor;
+        }
+        let half = (2 * rest as i128).cmp(&(self.denom as i128));
+        match (mode, half) {
+            (RoundingMode::Floor, _) => floor,
+            (RoundingMode::Ceil, _) => floor + 1,
+            (RoundingMode::TowardZero, _) => if self.numer < 0 { floor + 1 } else { floor },
+            (RoundingMode::AwayFromZero, _) => if self.numer < 0 { floor } else { floor + 1 },
+            (_, Ordering::Less) => floor,
+            (_, Ordering::Greater) => floor + 1,
+            (RoundingMode::HalfUp, Ordering::Equal) => floor + 1,
+            (RoundingMode::HalfDown, Ordering::Equal) => floor,
+            (RoundingMode::HalfEven, Ordering::Equal) => floor + floor.rem_euclid(2),
+        }
+    }
+
+    fn recip(&self) -> Rational {
+        assert!(self.numer != 0, "zero has no reciprocal");
+        Rational::new(self.denom, self.numer)
+    }
+
+    fn parallel(values: &[Rational]) -> Option<Rational> {
+        if values.is_empty() || values.iter().any(|v| v.numer == 0) {
+            return None;
+        }
+        Some(values.iter().map(|v| v.recip()).sum::<Rational>().recip())
+    }
+
+    // the two ancestors whose mediant is this node: [a0; ..., a(n-1)] and [a0; ..., an - 1]
+    fn stern_brocot_bounds(&self) -> (Rational, Rational) {
+        assert!(self.numer > 0, "only positive fractions are in the Stern-Brocot tree");
+        let mut terms = self.to_continued_fraction();
+        let a = Rational::from_continued_fraction(&terms[..terms.len() - 1]);
+        *terms.last_mut().unwrap() -= 1;
+        let b = Rational::from_continued_fraction(&terms);
+        if a < b { (a, b) } else { (b, a) }
+    }
+
+    // the path from 1/1 is R^a0 L^a1 R^a2 ... with the last run one short; R is a 1 bit
+    fn stern_brocot_index(&self) -> Option<u64> {
+        if self.numer <= 0 {
+            return None;
+        }
+        let mut terms = self.to_continued_fraction();
+        *terms.last_mut().unwrap() -= 1;
+        let mut index: u64 = 1;
+        for (i, &run) in terms.iter().enumerate() {
+            let bit = if i % 2 == 0 { 1 } else { 0 };
+            for _ in 0..run {
+                index = index.checked_mul(2)?.checked_add(bit)?;
+            }
+        }
+        Some(index)
+    }
+
+    // each step walks one whole run of same-side mediants along the Stern-Brocot path
+    // of value, measured with the doubling then bisecting search of steps_to_reach;
+    // a run ends at a convergent of value, so every step lands closer than the last
+    fn refine(&self, value: f64, extra_steps: u32) -> Rational {
+        let bounds = if self.numer > 0 { Some(self.stern_brocot_bounds()) } else { None };
+        let (mut lower_bound, mut upper_bound) = match bounds {
+            Some((lower, upper)) if lower.value() < value && value < upper.value() => {
+                if self.value() == value {
+                    return *self;
+                }
+                if self.value() < value { (*self, upper) } else { (lower, *self) }
+            }
+            // self is not in the tree or not on the path to value, so start again from the root
+            _ => (Rational::new(0, 1), Rational::new(1, 0)),
+        };
+        let error = |r: &Rational| (r.value() - value).abs();
+        let mut best = *self;
+        for _ in 0..extra_steps {
+            let numer = lower_bound.numer.checked_add(upper_bound.numer);
+            let Some((numer, denom)) = numer.zip(lower_bound.denom.checked_add(upper_bound.denom)) else {
+                break;
+            };
+            let m = Rational::raw(numer, denom);
+            let m_value: f64 = m.value();
+            if m_value == value {
+                return m;
+            }
+            let (toward, below) = if m_value < value { (upper_bound, true) } else { (lower_bound, false) };
+            let same_side = |k: i64| {
+                let numer = k.checked_mul(toward.numer).and_then(|n| n.checked_add(m.numer));
+                let denom = k.checked_mul(toward.denom).and_then(|d| d.checked_add(m.denom));
+                numer.zip(denom).is_some_and(|(numer, denom)| {
+                    let k_value = Rational::raw(numer, denom).value();
+                    if below { k_value < value } else { k_value > value }
+                })
+            };
+            let (mut lo, mut hi): (i64, i64) = (0, 1);
+            while same_side(hi) {
+                lo = hi;
+                hi = hi.saturating_mul(2);
+            }
+            while hi - lo > 1 {
+                let mid = lo + (hi - lo) / 2;
+                if same_side(mid) {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+            let run_end = Rational::new(m.numer + lo * toward.numer, m.denom + lo * toward.denom);
+            if error(&run_end) < error(&best) {
+                best = run_end;
+            }
+            if below {
+                lower_bound = run_end;
+            } else {
+                upper_bound = run_end;
+            }
+        }
+        best
+    }
+
+    // a random walk over the nodes of [lo, hi] with denominator <= max_denom: it starts at
+    // the simplest one and at every node either stops there or moves to the nearest
+    // in-range descendant on the left or right, each option that exists being equally
+    // likely; a node's probability is the product of 1/options along its path, so simple
+    // fractions are favoured and the result is not uniform. None if no node fits.
+    // Without a manifest there is no rand crate and no feature to gate it behind, so
+    // rather than taking a rand::Rng it draws its choices from a caller's bit source
+    fn random_in_range(
+        lo: &Rational,
+        hi: &Rational,
+        random_bit: &mut impl FnMut() -> bool,
+        max_denom: i64,
+    ) -> Option<Rational> {
+        let first_in_range = |mut lower_bound: Rational, mut upper_bound: Rational| loop {
+            let m: Rational = lower_bound.mediant(&upper_bound);
+            if m.denom > max_denom {
+                break None;
+            }
+            if m < *lo {
+                lower_bound = m;
+            } else if m > *hi {
+                upper_bound = m;
+            } else {
+                break Some((m, lower_bound, upper_bound));
+            }
+        };
+        let mut node = first_in_range(Rational::new(0, 1), Rational::new(1, 0))?;
+        loop {
+            let (m, lower_bound, upper_bound) = node;
+            let children: Vec<_> = [first_in_range(lower_bound, m), first_in_range(m, upper_bound)]
+                .into_iter()
+                .flatten()
+                .collect();
+            // option 0 stops at m; draw it uniformly from the bits by rejection
+            let options = children.len() + 1;
+            let choice = loop {
+                let draw = (0..usize::BITS - (options - 1).leading_zeros())
+                    .fold(0, |acc, _| acc << 1 | random_bit() as usize);
+                if draw < options {
+                    break draw;
+                }
+            };
+            if choice == 0 {
+                break Some(m);
+            }
+            node = children[choice - 1];
+        }
+    }
+
+    fn subtree_iter(&self, depth: u32) -> impl Iterator<Item = Rational> {
+        let (lower_bound, upper_bound) = self.stern_brocot_bounds();
+        let mut queue: VecDeque<(Rational, Rational, Rational, u32)> = VecDeque::new();
+        queue.push_back((lower_bound, *self, upper_bound, 0));
+        iter::from_fn(move || {
+            let (lower_bound, node, upper_bound, level) = queue.pop_front()?;
+            if level < depth {
+                queue.push_back((lower_bound, lower_bound.mediant(&node), node, level + 1));
+                queue.push_back((node, node.mediant(&upper_bound), upper_bound, level + 1));
+            }
+            Some(node)
+        })
+    }
+
+    // the wrapping_* family wraps the raw i64 fields and skips reduction, so the result
+    // is generally not a valid fraction: it may be unreduced, negative-denominator or x/0
+    fn wrapping_add(&self, other: &Rational) -> Rational {
+        Rational::raw(
+            self.numer.wrapping_mul(other.denom).wrapping_add(self.denom.wrapping_mul(other.numer)),
+            self.denom.wrapping_mul(other.denom),
+        )
+    }
+
+    fn wrapping_sub(&self, other: &Rational) -> Rational {
+        Rational::raw(
+            self.numer.wrapping_mul(other.denom).wrapping_sub(self.denom.wrapping_mul(other.numer)),
+            self.denom.wrapping_mul(other.denom),
+        )
+    }
+
+    fn wrapping_mul(&self, other: &Rational) -> Rational {
+        Rational::raw(self.numer.wrapping_mul(other.numer), self.denom.wrapping_mul(other.denom))
+    }
+
+    fn wrapping_div(&self, other: &Rational) -> Rational {
+        Rational::raw(self.numer.wrapping_mul(other.denom), self.denom.wrapping_mul(other.numer))
+    }
+
+    fn common_denominator(values: &[Rational]) -> Option<i64> {
+        let mut lcd: i64 = 1;
+        for v in values {
+            lcd = (lcd / Rational::gcd(lcd, v.denom)).checked_mul(v.denom)?;
+        }
+        Some(lcd)
+    }
+
+    fn to_common_denominator(values: &[Rational]) -> Option<Vec<(i64, i64)>> {
+        let lcd = Rational::common_denominator(values)?;
+        values
+            .iter()
+            .map(|v| Some((v.numer.checked_mul(lcd / v.denom)?, lcd)))
+            .collect()
+    }
+
+    fn log2_bracket(&self) -> Option<(i32, i32)> {
+        if self.numer <= 0 {
+            return None;
+        }
+        let (numer, denom) = (self.numer as i128, self.denom as i128);
+        let k = self.denom.leading_zeros() as i32 - self.numer.leading_zeros() as i32;
+        let at_least = if k >= 0 { numer >= denom << k } else { numer << -k >= denom };
+        let k = if at_least { k } else { k - 1 };
+        Some((k, k + 1))
+    }
+
+    // stops at the first multiple that overflows, so it may return fewer than count
+    fn equivalent_fractions(&self, count: usize) -> Vec<(i64, i64)> {
+        (1..)
+            .take(count)
+            .map_while(|k: i64| Some((k.checked_mul(self.numer)?, k.checked_mul(self.denom)?)))
+            .collect()
+    }
+
+    // None when the reduced denominator does not fit in i64; any nonzero value with
+    // a scale past 10^38 is at least that fine-grained, so checked_pow failing agrees
+    fn from_scaled(value: i64, scale: u32) -> Option<Rational> {
+        if value == 0 {
+            return Some(Rational::new(0, 1));
+        }
+        Rational::from_i128(value as i128, 10i128.checked_pow(scale)?)
+    }
+
+    fn harmonic_number(n: u32) -> Option<Rational> {
+        let terms: Vec<Rational> = (1..=n as i64).map(|k| Rational::new(1, k)).collect();
+        Rational::sum_exact(&terms)
+    }
+
+    fn sum_is_integer(&self, other: &Rational) -> bool {
+        let (sd, od) = (self.denom as i128, other.denom as i128);
+        (self.numer as i128 * od + sd * other.numer as i128) % (sd * od) == 0
+    }
+
+    // long division in base 2 of the wrap_unit part, so the integer part is ignored
+    fn binary_expansion(&self, max_bits: u32) -> (Vec<bool>, bool) {
+        let denom = self.denom as i128;
+        let mut remainder = self.numer.rem_euclid(self.denom) as i128;
+        let mut bits: Vec<bool> = Vec::new();
+        while remainder != 0 && bits.len() < max_bits as usize {
+            remainder *= 2;
+            bits.push(remainder >= denom);
+            if remainder >= denom {
+                remainder -= denom;
+            }
+        }
+        (bits, remainder == 0)
+    }
+
+    fn terminates_in_base(&self, base: i64) -> bool {
+        assert!(base >= 2, "base must be at least 2");
+        let mut denom = self.denom;
+        loop {
+            let g = Rational::gcd(denom, base);
+            if g == 1 {
+                break denom == 1;
+            }
+            denom /= g;
+        }
+    }
+
+    // integer_bits may carry non-repeating fractional bits after a point, so "0.1" with
+    // repeating "10" is 0.1101010... = 5/6; an empty repeating part means no repetition
+    fn from_binary_fraction(integer_bits: &str, repeating_bits: &str) -> Result<Rational, RationalError> {
+        let (whole_bits, fixed_bits) = integer_bits.split_once('.').unwrap_or((integer_bits, ""));
+        let bits = [whole_bits, fixed_bits, repeating_bits];
+        if whole_bits.is_empty() || !bits.iter().all(|b| b.chars().all(|c| c == '0' || c == '1')) {
+            return Err(RationalError::InvalidFormat);
+        }
+        let overflow = || RationalError::Overflow(OverflowDirection::Positive);
+        if fixed_bits.len() + repeating_bits.len() > 62 {
+            return Err(overflow());
+        }
+        let parse = |b: &str| if b.is_empty() { Ok(0) } else { i128::from_str_radix(b, 2) };
+        let whole = parse(whole_bits).map_err(|_| overflow())?;
+        let (fixed, repeating) = (parse(fixed_bits).unwrap(), parse(repeating_bits).unwrap());
+        // 0.F(R) = (F * (2^r - 1) + R) / (2^f * (2^r - 1)), with 2^r - 1 read as 1 when r == 0
+        let period = ((1i128 << repeating_bits.len()) - 1).max(1);
+        let denom = (1i128 << fixed_bits.len()) * period;
+        let numer = whole.checked_mul(denom).and_then(|w| w.checked_add(fixed * period + repeating));
+        numer.and_then(|numer| Rational::from_i128(numer, denom)).ok_or_else(overflow)
+    }
+
+    // u64 because the period of a prime denominator p can be as large as p - 1
+    fn decimal_period(&self) -> u64 {
+        let mut denom = self.denom;
+        while denom % 2 == 0 {
+            denom /= 2;
+        }
+        while denom % 5 == 0 {
+            denom /= 5;
+        }
+        if denom == 1 {
+            0
+        } else {
+            Rational::multiplicative_order(10, denom)
+        }
+    }
+
+    // the orbit of x -> 2x mod 1 only depends on the denominator, so values outside
+    // [0, 1) get the period of their wrap_unit
+    fn doubling_map_period(&self) -> Option<u64> {
+        if self.is_dyadic() {
+            return None;
+        }
+        let odd_part = self.denom >> self.denom.trailing_zeros();
+        Some(Rational::multiplicative_order(2, odd_part))
+    }
+
+    // smallest k > 0 with base^k == 1 (mod modulus), for coprime base and modulus > 1;
+    // the order divides phi(modulus), so start there and divide out each prime of phi
+    // for as long as the power stays 1
+    fn multiplicative_order(base: i64, modulus: i64) -> u64 {
+        let modulus = modulus as u64;
+        let base = base.rem_euclid(modulus as i64) as u64;
+        let phi: u64 = Rational::prime_factors(modulus)
+            .into_iter()
+            .map(|(p, k)| p.pow(k - 1) * (p - 1))
+            .product();
+        let mut order = phi;
+        for (q, _) in Rational::prime_factors(phi) {
+            while order.is_multiple_of(q) && Rational::pow_mod(base, order / q, modulus) == 1 {
+                order /= q;
+            }
+        }
+        order
+    }
+
+    fn pow_mod(base: u64, mut exp: u64, modulus: u64) -> u64 {
+        let (mut result, mut base) = (1 % modulus as u128, base as u128 % modulus as u128);
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result * base % modulus as u128;
+            }
+            base = base * base % modulus as u128;
+            exp >>= 1;
+        }
+        result as u64
+    }
+
+    // deterministic Miller-Rabin: these witnesses decide every n < 2^64
+    fn is_prime(n: u64) -> bool {
+        const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+        if n < 2 {
+            return false;
+        }
+        if let Some(&p) = WITNESSES.iter().find(|&&p| n.is_multiple_of(p)) {
+            return n == p;
+        }
+        let s = (n - 1).trailing_zeros();
+        let d = (n - 1) >> s;
+        WITNESSES.iter().all(|&a| {
+            let mut x = Rational::pow_mod(a, d, n);
+            if x == 1 || x == n - 1 {
+                return true;
+            }
+            for _ in 1..s {
+                x = (x as u128 * x as u128 % n as u128) as u64;
+                if x == n - 1 {
+                    return true;
+                }
+            }
+            false
+        })
+    }
+
+    // (prime, exponent) pairs in increasing prime order, split by Pollard's rho so that
+    // large prime factors cost about n^(1/4) steps instead of trial division's n^(1/2)
+    fn prime_factors(n: u64) -> Vec<(u64, u32)> {
+        let mut primes: Vec<u64> = Vec::new();
+        let mut pending: Vec<u64> = vec![n];
+        while let Some(m) = pending.pop() {
+            if m == 1 {
+                continue;
+            }
+            if Rational::is_prime(m) {
+                primes.push(m);
+                continue;
+            }
+            let d = Rational::pollard_rho(m);
+            pending.push(d);
+            pending.push(m / d);
+        }
+        primes.sort_unstable();
+        let mut factors: Vec<(u64, u32)> = Vec::new();
+        for p in primes {
+            match factors.last_mut() {
+                Some((q, k)) if *q == p => *k += 1,
+                _ => factors.push((p, 1)),
+            }
+        }
+        factors
+    }
+
+    // a nontrivial factor of a composite n, via Floyd cycle detection on x -> x^2 + c
+    fn pollard_rho(n: u64) -> u64 {
+        if n.is_multiple_of(2) {
+            return 2;
+        }
+        let gcd = |mut a: u64, mut b: u64| {
+            while b != 0 {
+                (a, b) = (b, a % b);
+            }
+            a
+        };
+        (1..)
+            .find_map(|c: u64| {
+                let step = |x: u64| ((x as u128 * x as u128 + c as u128) % n as u128) as u64;
+                let (mut x, mut y, mut d) = (2, 2, 1);
+                while d == 1 {
+                    x = step(x);
+                    y = step(step(y));
+                    d = gcd(x.abs_diff(y), n);
+                }
+                Some(d).filter(|&d| d != n)
+            })
+            .unwrap()
+    }
+
+    // the even case averages the middle pair through mean, so it is only None for
+    // empty input or when that average does not fit in i64
+    fn median(values: &mut [Rational]) -> Option<Rational> {
+        values.sort();
+        let mid = values.len() / 2;
+        if values.len() % 2 == 1 {
+            Some(values[mid])
+        } else {
+            Rational::mean(values.get(mid.checked_sub(1)?..=mid)?)
+        }
+    }
+
+    fn lerp(&self, other: &Rational, t: &Rational) -> Rational {
+        let (n1, d1) = (self.numer as i128, self.denom as i128);
+        let (n2, d2) = (other.numer as i128, other.denom as i128);
+        let reduce = |numer: i128, denom: i128| {
+            let g = Rational::gcd_i128(numer, denom);
+            (numer / g, denom / g)
+        };
+        let (numer, denom) = reduce(n2 * d1 - n1 * d2, d1 * d2);
+        let (numer, denom) = reduce(
+            numer.checked_mul(t.numer as i128).expect("lerp overflows i128"),
+            denom.checked_mul(t.denom as i128).expect("lerp overflows i128"),
+        );
+        let numer = numer
+            .checked_mul(d1)
+            .and_then(|n| n.checked_add(n1.checked_mul(denom)?))
+            .expect("lerp overflows i128");
+        let denom = denom.checked_mul(d1).expect("lerp overflows i128");
+        Rational::from_i128(numer, denom).expect("lerp result does not fit in i64")
+    }
+
+    fn cmp_by_simplicity(&self, other: &Rational) -> Ordering {
+        self.denom
+            .cmp(&other.denom)
+            .then(self.numer.unsigned_abs().cmp(&other.numer.unsigned_abs()))
+    }
+
+    fn mean(values: &[Rational]) -> Option<Rational> {
+        if values.is_empty() {
+            return None;
+        }
+        let (numer, denom) = Rational::sum_i128(values)?;
+        Rational::from_i128(numer, denom.checked_mul(values.len() as i128)?)
+    }
+
+    fn sign_of_product(&self, other: &Rational) -> i64 {
+        self.numer.signum() * other.numer.signum()
+    }
+
+    fn sign_of_quotient(&self, other: &Rational) -> i64 {
+        assert!(other.numer != 0, "division by zero");
+        self.numer.signum() * other.numer.signum()
+    }
+
+    fn divides(&self, other: &Rational) -> bool {
+        self.numer != 0
+            && (other.numer as i128 * self.denom as i128) % (other.denom as i128 * self.numer as i128) == 0
+    }
+
+    fn eval_polynomial(coeffs: &[Rational], x: &Rational) -> Rational {
+        coeffs
+            .iter()
+            .rev()
+            .fold(Rational::new(0, 1), |acc, c| acc * *x + *c)
+    }
+
+    fn nearest_unit_fraction(&self) -> Result<Rational, RationalError> {
+        if self.numer <= 0 || self.numer > self.denom {
+            return Err(RationalError::OutOfRange);
+        }
+        // 1/(n+1) < p/q <= 1/n; n + 1 only overflows for n = i64::MAX, where p/q is 1/n
+        let n = self.denom / self.numer;
+        let Some(next) = n.checked_add(1) else {
+            return Ok(Rational::new(1, n));
+        };
+        // the distances are (q - n*p) / (n*q) and ((n+1)*p - q) / ((n+1)*q); compare
+        // them with q cancelled, both cross products fit an i128
+        let (p, q) = (self.numer as i128, self.denom as i128);
+        let above_error = (q - n as i128 * p) * next as i128;
+        let below_error = (next as i128 * p - q) * n as i128;
+        if above_error <= below_error {
+            Ok(Rational::new(1, n))
+        } else {
+            Ok(Rational::new(1, next))
+        }
+    }
+
+    fn classify(&self) -> FractionKind {
+        if self.numer == 0 {
+            FractionKind::Zero
+        } else if self.denom == 1 {
+            FractionKind::Integer
+        } else if self.numer == 1 {
+            FractionKind::Unit
+        } else if self.numer.abs() > self.denom {
+            FractionKind::Improper
+        } else if self.numer > 0 {
+            FractionKind::ProperPositive
+        } else {
+            FractionKind::ProperNegative
+        }
+    }
+
+    // ?([a0; a1, a2, ...]) = a0 + 2 * (2^-a1 - 2^-(a1+a2) + 2^-(a1+a2+a3) - ...)
+    fn minkowski_question_mark(&self) -> f64 {
+        let terms = self.to_continued_fraction();
+        let mut depth: i64 = 0;
+        let mut sum: f64 = 0.0;
+        for (k, &a) in terms.iter().enumerate().skip(1) {
+            depth += a;
+            let term = 0.5f64.powi(depth.min(2000) as i32);
+            sum += if k % 2 == 1 { term } else { -term };
+        }
+        terms[0] as f64 + 2.0 * sum
+    }
+
+    fn compare_detailed(&self, other: &Rational) -> (Ordering, i128) {
+        let difference = self.numer as i128 * other.denom as i128 - other.numer as i128 * self.denom as i128;
+        (difference.cmp(&0), difference)
+    }
+
+    // every divisor is a product of prime powers p^0..=p^k over the factorization
+    fn denominator_divisors(&self) -> Vec<i64> {
+        let mut divisors: Vec<i64> = vec![1];
+        for (p, k) in Rational::prime_factors(self.denom as u64) {
+            let p = p as i64;
+            let mut multiples: Vec<i64> = Vec::new();
+            for &d in &divisors {
+                let mut m = d;
+                for _ in 0..k {
+                    m *= p;
+                    multiples.push(m);
+                }
+            }
+            divisors.extend(multiples);
+        }
+        divisors.sort_unstable();
+        divisors
+    }
+
+    // swapped bounds are normalized, so is_between(lo, hi) == is_between(hi, lo)
+    fn is_between(&self, lo: &Rational, hi: &Rational) -> bool {
+        let (lo, hi) = if lo <= hi { (lo, hi) } else { (hi, lo) };
+        lo <= self && self <= hi
+    }
+
+    // a vertical line (run == 0, rise included) has no slope, as new_raw rejects it
+    fn from_slope_angle(rise: i64, run: i64) -> Result<Rational, RationalError> {
+        if run == 0 {
+            return Err(RationalError::ZeroDenominator);
+        }
+        Rational::try_from_i128(rise as i128, run as i128)
+    }
+
+    // with hypotenuse h = sqrt(p^2 + q^2) the angle has sin = p/h and cos = q/h, so
+    // t = sin/(1 + cos) = p/(h + q); None unless (p, q, h) is a Pythagorean triple
+    fn slope_to_tangent_halfangle(&self) -> Option<Rational> {
+        let square = (self.numer as i128).pow(2) + (self.denom as i128).pow(2);
+        let hypotenuse = Rational::integer_root(i64::try_from(square).ok()?, 2)?;
+        Rational::from_i128(self.numer as i128, hypotenuse as i128 + self.denom as i128)
+    }
+
+    // the reduced product as an i128 pair, which always fits
+    fn product_i128(&self, other: &Rational) -> (i128, i128) {
+        let numer = self.numer as i128 * other.numer as i128;
+        let denom = self.denom as i128 * other.denom as i128;
+        let g = Rational::gcd_i128(numer, denom);
+        (numer / g, denom / g)
+    }
+
+    fn det2x2(a: &Rational, b: &Rational, c: &Rational, d: &Rational) -> Rational {
+        let ((ad_numer, ad_denom), (bc_numer, bc_denom)) = (a.product_i128(d), b.product_i128(c));
+        let g = Rational::gcd_i128(ad_denom, bc_denom);
+        let numer = ad_numer
+            .checked_mul(bc_denom / g)
+            .zip(bc_numer.checked_mul(ad_denom / g))
+            .and_then(|(x, y)| x.checked_sub(y));
+        let denom = (ad_denom / g).checked_mul(bc_denom);
+        numer
+            .zip(denom)
+            .and_then(|(numer, denom)| Rational::from_i128(numer, denom))
+            .expect("determinant does not fit in i64")
+    }
+
+    // None when a == 0, whether the equation then has no solution (b != 0) or every x
+    // solves it (b == 0), and when -b/a does not fit in i64
+    fn solve_linear(a: &Rational, b: &Rational) -> Option<Rational> {
+        if a.numer == 0 {
+            return None;
+        }
+        Rational::from_i128(-(b.numer as i128) * a.denom as i128, b.denom as i128 * a.numer as i128)
+    }
+
+    // None for a degenerate segment, or when t does not fit in i64
+    fn barycentric(point: &Rational, start: &Rational, end: &Rational) -> Option<Rational> {
+        let offset = point.checked_sub(start).ok()?;
+        let length = end.checked_sub(start).ok()?;
+        if length.numer == 0 {
+            return None;
+        }
+        Rational::from_i128(offset.numer as i128 * length.denom as i128, offset.denom as i128 * length.numer as i128)
+    }
+
+    fn and(&self, other: &Rational) -> Result<Rational, RationalError> {
+        self.check_probability()?;
+        other.check_probability()?;
+        self.checked_mul(other)
+    }
+
+    // a + b - a*b computed as 1 - (1 - a)(1 - b), which needs no intermediate sums
+    fn or(&self, other: &Rational) -> Result<Rational, RationalError> {
+        self.check_probability()?;
+        other.check_probability()?;
+        let neither = (self.denom - self.numer) as i128 * (other.denom - other.numer) as i128;
+        let denom = self.denom as i128 * other.denom as i128;
+        Rational::try_from_i128(denom - neither, denom)
+    }
+
+    fn check_probability(&self) -> Result<(), RationalError> {
+        if self.numer < 0 || self.numer > self.denom {
+            Err(RationalError::OutOfRange)
+        } else {
+            Ok(())
+        }
+    }
+
+    // multiplying through by self.denom gives (a*p + b*q)/(c*p + d*q) for self = p/q;
+    // None at the pole c*self + d == 0 or when the image does not fit in i64
+    fn mobius(&self, a: i64, b: i64, c: i64, d: i64) -> Option<Rational> {
+        let (p, q) = (self.numer as i128, self.denom as i128);
+        let denom = c as i128 * p + d as i128 * q;
+        if denom == 0 {
+            return None;
+        }
+        Rational::from_i128(a as i128 * p + b as i128 * q, denom)
+    }
+
+    // None when a == d or b == c, or when the ratio does not fit in i64
+    fn cross_ratio(a: &Rational, b: &Rational, c: &Rational, d: &Rational) -> Option<Rational> {
+        let difference = |x: &Rational, y: &Rational| x.checked_sub(y).ok();
+        let (top_numer, top_denom) = difference(a, c)?.product_i128(&difference(b, d)?);
+        let (bottom_numer, bottom_denom) = difference(a, d)?.product_i128(&difference(b, c)?);
+        if bottom_numer == 0 {
+            return None;
+        }
+        let g_numer = Rational::gcd_i128(top_numer, bottom_numer);
+        let g_denom = Rational::gcd_i128(top_denom, bottom_denom);
+        let numer = (top_numer / g_numer).checked_mul(bottom_denom / g_denom)?;
+        let denom = (top_denom / g_denom).checked_mul(bottom_numer / g_numer)?;
+        Rational::from_i128(numer, denom)
+    }
+
+    // one summand a_i/q_i per prime power q_i of the denominator, with a_i = a * (n/q_i)^-1
+    // (mod q_i) for self = a/n; the integer left over is absorbed into the last summand
+    fn decompose_over_prime_powers(&self) -> Vec<Rational> {
+        let (a, n) = (self.numer as i128, self.denom as i128);
+        let prime_powers: Vec<i128> = Rational::prime_factors(self.denom as u64)
+            .into_iter()
+            .map(|(p, k)| (p as i128).pow(k))
+            .collect();
+        if prime_powers.is_empty() {
+            return vec![*self];
+        }
+        let mut numers: Vec<i128> = prime_powers
+            .iter()
+            .map(|&q| (a.rem_euclid(q) * Rational::mod_inverse(n / q, q)).rem_euclid(q))
+            .collect();
+        let total: i128 = numers.iter().zip(&prime_powers).map(|(&a_i, &q)| a_i * (n / q)).sum();
+        *numers.last_mut().unwrap() += (a - total) / n * prime_powers.last().unwrap();
+        numers
+            .into_iter()
+            .zip(prime_powers)
+            .map(|(numer, q)| Rational::new(numer as i64, q as i64))
+            .collect()
+    }
+
+    // n steps of x -> a*x + b from self, failing on the first step that leaves i64
+    fn iterate_map(&self, a: &Rational, b: &Rational, n: u32) -> Result<Rational, RationalError> {
+        let mut x = *self;
+        for _ in 0..n {
+            x = a.checked_mul(&x)?.checked_add(b)?;
+        }
+        Ok(x)
+    }
+
+    fn dot(a: &[Rational], b: &[Rational]) -> Option<Rational> {
+        if a.len() != b.len() {
+            return None;
+        }
+        let mut sum = Rational::new(0, 1);
+        for (x, y) in a.iter().zip(b.iter()) {
+            sum = sum + *x * *y;
+        }
+        Some(sum)
+    }
+
+    // the area under the step function with these heights over consecutive widths
+    fn weighted_interval_sum(heights: &[Rational], widths: &[Rational]) -> Option<Rational> {
+        Rational::dot(heights, widths)
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+struct AlgoComparison {
+    from_result: Rational,
+    from_steps: u64,
+    from_time: Duration,
+    fast_from_result: Rational,
+    fast_from_steps: u64,
+    fast_from_time: Duration,
+    identical: bool,
+}
+
+// HalfUp and HalfDown break ties toward positive and negative infinity
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+enum RoundingMode {
+    HalfUp,
+    HalfDown,
+    HalfEven,
+    TowardZero,
+    AwayFromZero,
+    Floor,
+    Ceil,
+}
+
+// checked in declaration order, so 1/1 is an Integer and only positive 1/n is a Unit
+#[allow(dead_code)]
+#[derive(Debug, PartialEq)]
+enum FractionKind {
+    Zero,
+    Integer,
+    Unit,
+    Improper,
+    ProperPositive,
+    ProperNegative,
+}
+
+// the fraction carries the same sign as the whole part
+#[allow(dead_code)]
+struct MixedNumber {
+    whole: i64,
+    fraction: Rational,
+}
+
+impl fmt::Display for MixedNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.fraction.numer == 0 {
+            write!(f, "{}", self.whole)
+        } else if self.whole == 0 {
+            write!(f, "{}/{}", self.fraction.numer, self.fraction.denom)
+        } else {
+            write!(f, "{} {}/{}", self.whole, self.fraction.numer.abs(), self.fraction.denom)
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, PartialEq)]
+enum SearchStatus {
+    Continue(Rational),
+    Done(Rational),
+}
+
+// the loop of from unrolled one mediant per step; once Done, further steps repeat it
+#[allow(dead_code)]
+struct SternBrocotSearch {
+    value: f64,
+    error_bound: f64,
+    lower_bound: Rational,
+    upper_bound: Rational,
+    result: Option<Rational>,
+}
+
+#[allow(dead_code)]
+impl SternBrocotSearch {
+    fn new(value: f64, error_bound: f64) -> SternBrocotSearch {
+        SternBrocotSearch {
+            value,
+            error_bound,
+            lower_bound: Rational::new(0, 1),
+            upper_bound: Rational::new(1, 0),
+            result: None,
+        }
+    }
+
+    fn step(&mut self) -> SearchStatus {
+        if let Some(result) = self.result {
+            return SearchStatus::Done(result);
+        }
+        let m: Rational = self.lower_bound.mediant(&self.upper_bound);
+        let m_value: f64 = m.value();
+        if m_value < self.value - self.error_bound {
+            self.lower_bound = m;
+        } else if m_value > self.value + self.error_bound {
+            self.upper_bound = m;
+        } else {
+            self.result = Some(m);
+            return SearchStatus::Done(m);
+        }
+        SearchStatus::Continue(m)
+    }
+}
+
+impl PartialEq for Rational {
+    fn eq(&self, other: &Rational) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Rational {}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Rational) -> Ordering {
+        self.compare_detailed(other).0
+    }
+}
+
+#[allow(dead_code)]
+struct OrderedRational(Rational);
+
+impl PartialEq for OrderedRational {
+    fn eq(&self, other: &OrderedRational) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for OrderedRational {}
+
+impl PartialOrd for OrderedRational {
+    fn partial_cmp(&self, other: &OrderedRational) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedRational {
+    fn cmp(&self, other: &OrderedRational) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl ops::Add<Rational> for Rational {
+    type Output = Rational;
+
+    fn add(self, rhs: Rational) -> Rational {
+        Rational::new(
+            self.numer * rhs.denom + self.denom * rhs.numer,
+            self.denom * rhs.denom,
+        )
+    }
+}
+
+impl ops::Sub<Rational> for Rational {
+    type Output = Rational;
+
+    fn sub(self, rhs: Rational) -> Rational {
+        Rational::new(
+            self.numer * rhs.denom - self.denom * rhs.numer,
+            self.denom * rhs.denom,
+        )
+    }
+}
+
+impl ops::Mul<Rational> for Rational {
+    type Output = Rational;
+
+    fn mul(self, rhs: Rational) -> Rational {
+        Rational::new(self.numer * rhs.numer, self.denom * rhs.denom)
+    }
+}
+
+impl ops::Div<Rational> for Rational {
+    type Output = Rational;
+
+    fn div(self, rhs: Rational) -> Rational {
+        Rational::new(self.numer * rhs.denom, self.denom * rhs.numer)
+    }
+}
+
+impl iter::Sum for Rational {
+    fn sum<I: Iterator<Item = Rational>>(iter: I) -> Rational {
+        iter.fold(Rational::new(0, 1), |a, b| a + b)
+    }
+}
+
+impl ops::Neg for Rational {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Rational::new(-self.numer, self.denom)
+    }
+}
+
+impl ops::Neg for &Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Self::Output {
+        Rational::new(-self.numer, self.denom)
+    }
+}
+
+fn main() {
+    println!("{:?}", Rational::new(2, 5) / Rational::new(1, 2));
+    println!("{:?}", Rational::new(2, 7) * Rational::new(1, 2));
+    println!("{:?}", Rational::new(2, 3) + Rational::new(1, 2));
+    println!("{:?}", Rational::new(8, 5) - Rational::new(1, 2));
+    println!("{:?}\n", -Rational::new(2, 3));
+
+
+    measure_time(6.4285714285, 0.000000001);
+    measure_time(6.4285714285, 0.000000001);
+
+    measure_time(12_581_890_123.5384615384, 0.0000001);
+    measure_time(12_581_890_123.5384615384, 0.0000001);
+
+    measure_time(0.00000000000006 as f64, 0.000000000000001 as f64);
+    measure_time(0.00000000000006 as f64, 0.000000000000001 as f64);
+
+    measure_time(0.75 as f64, 0.0001 as f64);
+    measure_time(0.75 as f64, 0.0001 as f64);
+}
+
+
+fn measure_time(v: f64, err: f64) {
+    let start = Instant::now();
+    println!("{:?}", Rational::fast_from(v, err));
+    let end = Instant::now();
+    println!("{:?}", end.duration_since(start));
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_parts(r: Rational, numer: i64, denom: i64) {
+        assert_eq!((r.numer, r.denom), (numer, denom), "{:?}", r);
+    }
+
+    fn pairs(values: &[Rational]) -> Vec<(i64, i64)> {
+        values.iter().map(|r| (r.numer, r.denom)).collect()
+    }
+
+    // a fixed-seed generator, so the randomized tests see the same inputs every run
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            self.0 >> 33
+        }
+
+        fn range(&mut self, lo: i64, hi: i64) -> i64 {
+            lo + (self.next() % ((hi - lo + 1) as u64)) as i64
+        }
+
+        fn rat(&mut self, max: i64) -> Rational {
+            let mut denom = 0;
+            while denom == 0 {
+                denom = self.range(-max, max);
+            }
+            Rational::new(self.range(-max, max), denom)
+        }
+    }
+
+    #[test]
+    fn dot_sums_pairwise_products() {
+        let a = [Rational::new(1, 2), Rational::new(2, 3), Rational::new(3, 4)];
+        let b = [Rational::new(2, 1), Rational::new(3, 4), Rational::new(4, 9)];
+        assert_parts(Rational::dot(&a, &b).unwrap(), 11, 6);
+        assert!(Rational::dot(&a, &b[..2]).is_none());
+    }
+
+    #[test]
+    fn convergents_stop_at_the_denominator_limit() {
+        let c = Rational::convergents_up_to_denominator(std::f64::consts::PI, 200);
+        assert_eq!(pairs(&c), vec![(3, 1), (22, 7), (333, 106), (355, 113)]);
+        let c = Rational::convergents_up_to_denominator(std::f64::consts::PI, 100);
+        assert_eq!(pairs(&c), vec![(3, 1), (22, 7)]);
+        let c = Rational::convergents_up_to_denominator(0.75, 100);
+        assert_eq!(pairs(&c), vec![(1, 1), (3, 4)]);
+    }
+
+    #[test]
+    fn f64_bounds_bracket_the_exact_value() {
+        let (lo, hi) = Rational::new(1, 3).as_f64_bounds();
+        assert!(lo < hi && lo.next_up() == hi && lo <= 1.0 / 3.0 && 1.0 / 3.0 <= hi);
+        assert!(lo * 3.0 <= 1.0);
+        assert_eq!(Rational::new(1, 2).as_f64_bounds(), (0.5, 0.5));
+        assert_eq!(Rational::new(-3, 4).as_f64_bounds(), (-0.75, -0.75));
+        let (lo, hi) = Rational::new(-1, 3).as_f64_bounds();
+        assert!(lo < hi && lo.next_up() == hi && lo < -0.33 && hi > -0.34);
+        let (lo, hi) = Rational::new(i64::MAX, 3).as_f64_bounds();
+        assert!(lo < hi);
+        let (lo, hi) = Rational::new(1, i64::MAX).as_f64_bounds();
+        assert!(lo < hi);
+        let (lo, hi) = Rational::new(i64::MAX, 1).as_f64_bounds();
+        assert!(lo < hi, "{} {}", lo, hi);
+        assert_eq!(Rational::new(0, 1).as_f64_bounds(), (0.0, 0.0));
+        assert_eq!(Rational::new(1, 3).cmp_f64(f64::MIN_POSITIVE / 4.0), Ordering::Greater);
+        assert_eq!(Rational::new(-1, 3).cmp_f64(5e-324), Ordering::Less);
+        assert_eq!(Rational::new(0, 1).cmp_f64(-5e-324), Ordering::Greater);
+        assert_eq!(Rational::new(i64::MAX, 1).cmp_f64(1e300), Ordering::Less);
+        assert_eq!(Rational::new(i64::MIN + 1, 1).cmp_f64(-1e300), Ordering::Greater);
+        assert_eq!(Rational::new(3, 2).cmp_f64(1.5), Ordering::Equal);
+    }
+
+    #[test]
+    fn mixed_str_round_trips_through_to_mixed_string() {
+        assert_parts(Rational::from_mixed_str("3 1/2").unwrap(), 7, 2);
+        assert_parts(Rational::from_mixed_str("-3 1/2").unwrap(), -7, 2);
+        assert_parts(Rational::from_mixed_str("5").unwrap(), 5, 1);
+        assert_parts(Rational::from_mixed_str("3/6").unwrap(), 1, 2);
+        assert_eq!(
+            Rational::from_mixed_str("3 1/0").unwrap_err(),
+            RationalError::ZeroDenominator
+        );
+        assert_eq!(
+            Rational::from_mixed_str("1/0").unwrap_err(),
+            RationalError::ZeroDenominator
+        );
+        assert_eq!(
+            Rational::from_mixed_str("3 -1/2").unwrap_err(),
+            RationalError::InvalidFormat
+        );
+        assert_eq!(
+            Rational::from_mixed_str("").unwrap_err(),
+            RationalError::InvalidFormat
+        );
+        assert_eq!(
+            Rational::from_mixed_str("1 2 3").unwrap_err(),
+            RationalError::InvalidFormat
+        );
+        for &(n, d) in &[(7, 2), (-7, 2), (4, 1), (-4, 1), (1, 2), (-1, 2), (0, 1), (22, 7)] {
+            let s = Rational::new(n, d).to_mixed_string();
+            let back = Rational::from_mixed_str(&s).unwrap();
+            assert_eq!(back.to_mixed_string(), s);
+            assert_eq!(back.value(), n as f64 / d as f64, "{}", s);
+        }
+        assert_eq!(Rational::new(-7, 2).to_mixed_string(), "-3 1/2");
+        assert_eq!(Rational::new(-1, 2).to_mixed_string(), "-1/2");
+    }
+
+    #[test]
+    fn mixed_str_rejects_malformed_fractions() {
+        assert_parts(Rational::from_mixed_str("3 2/4").unwrap(), 7, 2);
+        assert_eq!(
+            Rational::from_mixed_str("3 1/-2").unwrap_err(),
+            RationalError::InvalidFormat
+        );
+        assert_eq!(
+            Rational::from_mixed_str("3 3").unwrap_err(),
+            RationalError::InvalidFormat
+        );
+    }
+
+    #[test]
+    fn mixed_str_reports_overflow() {
+        assert_eq!(
+            Rational::from_mixed_str("9223372036854775807 1/2").unwrap_err(),
+            RationalError::Overflow(OverflowDirection::Positive)
+        );
+        assert_eq!(
+            Rational::from_mixed_str("-9223372036854775808 1/2").unwrap_err(),
+            RationalError::Overflow(OverflowDirection::Negative)
+        );
+        assert_parts(
+            Rational::from_mixed_str("-9223372036854775808 0/1").unwrap(),
+            i64::MIN,
+            1,
+        );
+        assert_parts(
+            Rational::from_mixed_str("-4611686018427387903 1/2").unwrap(),
+            -9223372036854775807,
+            2,
+        );
+        assert_eq!(
+            Rational::parse_fraction("-9223372036854775808/-1").unwrap_err(),
+            RationalError::Overflow(OverflowDirection::Positive)
+        );
+        assert_parts(
+            Rational::parse_fraction("-9223372036854775808/1").unwrap(),
+            i64::MIN,
+            1,
+        );
+        assert_parts(
+            Rational::parse_fraction("-9223372036854775808/-2").unwrap(),
+            1 << 62,
+            1,
+        );
+    }
+
+    #[test]
+    fn calkin_wilf_is_breadth_first() {
+        assert_eq!(
+            pairs(&Rational::calkin_wilf(10)),
+            vec![
+                (1, 1),
+                (1, 2),
+                (2, 1),
+                (1, 3),
+                (3, 2),
+                (2, 3),
+                (3, 1),
+                (1, 4),
+                (4, 3),
+                (3, 5)
+            ]
+        );
+        assert!(Rational::calkin_wilf(0).is_empty());
+    }
+
+    #[test]
+    fn ordered_rational_keys_a_btree_map() {
+        use std::collections::BTreeMap;
+        let mut m = BTreeMap::new();
+        for (i, &(n, d)) in [(1, 2), (-1, 3), (2, 4), (5, 1), (1, -7), (0, 3)]
+            .iter()
+            .enumerate()
+        {
+            m.insert(OrderedRational(Rational::new(n, d)), i);
+        }
+        let keys: Vec<(i64, i64)> = m.keys().map(|k| (k.0.numer, k.0.denom)).collect();
+        assert_eq!(keys, vec![(-1, 3), (-1, 7), (0, 1), (1, 2), (5, 1)]);
+        assert_eq!(m[&OrderedRational(Rational::new(1, 2))], 2);
+        assert_parts(Rational::new(2, -4), -1, 2);
+        assert_parts(Rational::new(-2, -4), 1, 2);
+        assert_parts(Rational::new(0, -4), 0, 1);
+        assert_parts(Rational::new(3, 0), 1, 0);
+        assert!(Rational::new(1, 3) < Rational::new(1, 2));
+        assert_eq!(Rational::new(1, 3), Rational::new(2, 6));
+    }
+
+    #[test]
+    fn significant_digits_bound_the_relative_error() {
+        for &v in &[std::f64::consts::PI, 0.00123456, 6.4285714285, 1234.5678, 0.75] {
+            for d in 1..8 {
+                let r = Rational::from_significant_digits(v, d);
+                assert!(
+                    ((r.value() - v) / v).abs() <= 10f64.powi(-(d as i32)),
+                    "{} {} {:?}",
+                    v,
+                    d,
+                    r
+                );
+            }
+        }
+        assert_parts(Rational::from_significant_digits(std::f64::consts::PI, 3), 22, 7);
+    }
+
+    #[test]
+    fn reduce_in_place_normalizes_sign_and_zero() {
+        let mut r = Rational::raw(6, -8);
+        r.reduce_in_place();
+        assert_parts(r, -3, 4);
+        let mut r = Rational::raw(0, 7);
+        r.reduce_in_place();
+        assert_parts(r, 0, 1);
+    }
+
+    #[test]
+    fn invariants_hold_only_for_reduced_fractions() {
+        assert!(Rational::new(6, -8).invariants_hold());
+        assert!(Rational::new(0, 5).invariants_hold());
+        assert!(!Rational::raw(6, 8).invariants_hold());
+        assert!(!Rational::raw(3, -4).invariants_hold());
+        assert!(!Rational::raw(0, 5).invariants_hold());
+        assert!(!Rational::raw(1, 0).invariants_hold());
+    }
+
+    #[test]
+    fn arithmetic_laws_hold_on_a_grid() {
+        let grid: Vec<Rational> = (-4..=4)
+            .flat_map(|n| (1..=4).map(move |d| Rational::new(n, d)))
+            .collect();
+        let (zero, one) = (Rational::new(0, 1), Rational::new(1, 1));
+        for &a in &grid {
+            assert_eq!(a + zero, a);
+            assert_eq!(a * one, a);
+            for &b in &grid {
+                if a.numer != 0 && b.numer != 0 {
+                    assert_eq!(one / (a * b), (one / a) * (one / b));
+                }
+                for &c in &grid {
+                    let sum = (a + b) + c;
+                    assert_eq!(sum, a + (b + c));
+                    assert!(sum.invariants_hold(), "{:?}", sum);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn approximation_error_is_signed() {
+        let pi = std::f64::consts::PI;
+        let e = Rational::approximation_error(pi, 10);
+        assert_eq!(e.len(), 10);
+        assert_parts(e[6].0, 22, 7);
+        assert!((e[6].1 - (22.0 / 7.0 - pi)).abs() < 1e-15);
+        assert_parts(e[0].0, 3, 1);
+        assert!(e[0].1 < 0.0);
+    }
+
+    #[test]
+    fn neg_works_on_values_and_references() {
+        let r = Rational::new(3, -6);
+        assert_eq!(-(-r), r);
+        assert_eq!(-(&-(&r)), r);
+        assert_parts(-Rational::new(3, -6), 1, 2);
+        assert_parts(-&Rational::new(-4, -6), -2, 3);
+    }
+
+    #[test]
+    fn beats_and_simple_meters() {
+        assert_parts(Rational::new(3, 4).beats(4), 3, 1);
+        assert!(Rational::new(3, 4).is_simple_meter());
+        assert!(Rational::new(2, 2).is_simple_meter());
+        assert!(Rational::new(3, 8).is_simple_meter());
+        // 6/8 reduces to 3/4 before the check sees it
+        assert!(Rational::new(6, 8).is_simple_meter());
+        assert!(!Rational::new(9, 8).is_simple_meter());
+        assert!(!Rational::new(15, 16).is_simple_meter());
+        assert!(!Rational::new(5, 6).is_simple_meter());
+        assert!(!Rational::new(0, 4).is_simple_meter());
+        assert!(Rational::new(5, 8).is_dyadic() && !Rational::new(1, 3).is_dyadic());
+    }
+
+    #[test]
+    fn compact_strings_round_trip() {
+        let mut g = Lcg(7);
+        for _ in 0..5000 {
+            let r = g.rat(i64::MAX / 2);
+            let s = r.to_compact();
+            assert!(!s.contains(' '));
+            let back = Rational::from_compact(&s).unwrap();
+            assert_eq!((back.numer, back.denom), (r.numer, r.denom));
+        }
+        assert_eq!(Rational::new(8, 2).to_compact(), "4");
+        assert_eq!(Rational::new(-7, 2).to_compact(), "-7/2");
+        assert_eq!(
+            Rational::from_compact("1 1/2").unwrap_err(),
+            RationalError::InvalidFormat
+        );
+        assert_eq!(
+            Rational::from_compact("1/0").unwrap_err(),
+            RationalError::ZeroDenominator
+        );
+        assert!(Rational::from_compact("-9223372036854775808/-1").is_err());
+    }
+
+    #[test]
+    fn checked_sub_reports_the_overflow_direction() {
+        let max = Rational::new(i64::MAX, 1);
+        let min = Rational::new(i64::MIN + 1, 1);
+        assert_eq!(
+            max.checked_sub(&Rational::new(-1, 1)).unwrap_err(),
+            RationalError::Overflow(OverflowDirection::Positive)
+        );
+        assert_eq!(
+            min.checked_sub(&Rational::new(2, 1)).unwrap_err(),
+            RationalError::Overflow(OverflowDirection::Negative)
+        );
+        assert_parts(max.checked_sub(&max).unwrap(), 0, 1);
+        assert_parts(
+            Rational::new(i64::MAX, 2)
+                .checked_sub(&Rational::new(i64::MAX, 4))
+                .unwrap(),
+            i64::MAX,
+            4,
+        );
+        assert_parts(
+            Rational::new(1, 2).checked_sub(&Rational::new(3, 4)).unwrap(),
+            -1,
+            4,
+        );
+    }
+
+    #[test]
+    fn checked_ops_report_denominator_overflow() {
+        let tiny = Rational::new(1, i64::MAX);
+        assert_eq!(tiny.checked_mul(&tiny), Err(RationalError::DenominatorOverflow));
+        assert_eq!(
+            tiny.checked_sub(&Rational::new(1, i64::MAX - 1)),
+            Err(RationalError::DenominatorOverflow)
+        );
+        assert_eq!(
+            Rational::new(i64::MAX, 1).checked_add(&Rational::new(1, 1)),
+            Err(RationalError::Overflow(OverflowDirection::Positive))
+        );
+        assert_eq!(
+            Rational::new(i64::MIN + 1, 1).checked_sub(&Rational::new(2, 1)),
+            Err(RationalError::Overflow(OverflowDirection::Negative))
+        );
+        assert_eq!(
+            Rational::new(i64::MIN + 1, 1).checked_mul(&Rational::new(2, 1)),
+            Err(RationalError::Overflow(OverflowDirection::Negative))
+        );
+    }
+
+    #[test]
+    fn stern_brocot_depth_sums_continued_fraction_terms() {
+        assert_eq!(Rational::new(355, 113).to_continued_fraction(), vec![3, 7, 16]);
+        assert_eq!(Rational::new(-7, 2).to_continued_fraction(), vec![-4, 2]);
+        assert_eq!(Rational::new(0, 2).to_continued_fraction(), vec![0]);
+        assert_eq!(Rational::new(1, 1).stern_brocot_depth(), 0);
+        assert_eq!(Rational::new(1, 2).stern_brocot_depth(), 1);
+        assert_eq!(Rational::new(2, 3).stern_brocot_depth(), 2);
+        assert_eq!(Rational::new(355, 113).stern_brocot_depth(), 25);
+        assert_eq!(Rational::new(1, i64::MAX).stern_brocot_depth(), i64::MAX as u64 - 1);
+        assert_eq!(Rational::new(1, 1 << 33).stern_brocot_depth(), (1 << 33) - 1);
+        assert_eq!(Rational::new(i64::MAX, i64::MAX - 1).stern_brocot_depth(), i64::MAX as u64 - 1);
+    }
+
+    #[test]
+    fn from_is_within_the_error_bound() {
+        assert_rational_approx!(
+            Rational::from(6.4285714285, 0.000000001),
+            6.4285714285,
+            0.000000001
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "22/7 = 3.142857142857143 is")]
+    fn approx_assertion_names_the_fraction() {
+        assert_rational_approx!(Rational::new(22, 7), std::f64::consts::PI, 1e-6);
+    }
+
+    #[test]
+    fn try_pow_rational_takes_exact_roots() {
+        let r = |n, d| Rational::new(n, d);
+        assert_parts(r(4, 9).try_pow_rational(&r(1, 2)).unwrap(), 2, 3);
+        assert_parts(r(8, 27).try_pow_rational(&r(1, 3)).unwrap(), 2, 3);
+        assert_parts(r(-8, 27).try_pow_rational(&r(2, 3)).unwrap(), 4, 9);
+        assert_parts(r(4, 9).try_pow_rational(&r(-3, 2)).unwrap(), 27, 8);
+        assert_parts(r(4, 9).try_pow_rational(&r(0, 1)).unwrap(), 1, 1);
+        assert!(r(2, 1).try_pow_rational(&r(1, 2)).is_none());
+        assert!(r(-4, 1).try_pow_rational(&r(1, 2)).is_none());
+        assert!(r(0, 1).try_pow_rational(&r(-1, 1)).is_none());
+        assert_parts(
+            r(3037000499 * 3037000499, 1).try_pow_rational(&r(1, 2)).unwrap(),
+            3037000499,
+            1,
+        );
+        assert!(r(3037000499 * 3037000499 - 1, 1)
+            .try_pow_rational(&r(1, 2))
+            .is_none());
+        assert_parts(r(1 << 62, 1).try_pow_rational(&r(1, 62)).unwrap(), 2, 1);
+        assert!(r(3, 1).try_pow_rational(&r(1, 100)).is_none());
+        assert_parts(r(1, 1).try_pow_rational(&r(1, 100)).unwrap(), 1, 1);
+    }
+
+    #[test]
+    fn integer_root_is_exact() {
+        let big = (1i64 << 62) + 3;
+        assert_parts(
+            Rational::new(big, 1)
+                .try_pow_rational(&Rational::new(1, 1))
+                .unwrap(),
+            big,
+            1,
+        );
+        assert_eq!(Rational::integer_root(big, 1), Some(big));
+        assert_eq!(
+            Rational::integer_root(3037000499i64 * 3037000499, 2),
+            Some(3037000499)
+        );
+        assert_eq!(Rational::integer_root(3037000499i64 * 3037000499 - 1, 2), None);
+        assert_eq!(Rational::integer_root(i64::MAX, 2), None);
+        assert_eq!(Rational::integer_root(1 << 62, 62), Some(2));
+        assert_eq!(Rational::integer_root(1 << 62, 31), Some(4));
+        assert_eq!(Rational::integer_root(-(1 << 62), 31), Some(-4));
+        assert_eq!(Rational::integer_root(2097151i64.pow(3), 3), Some(2097151));
+        assert_eq!(Rational::integer_root(5, 0), None);
+        for r in 2..2000i64 {
+            for n in 2..6u32 {
+                if let Some(x) = r.checked_pow(n) {
+                    assert_eq!(Rational::integer_root(x, n as i64), Some(r));
+                    assert_eq!(Rational::integer_root(x + 1, n as i64), None);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn abs_diff_does_not_overflow_for_close_values() {
+        let a = Rational::new(i64::MAX, 3);
+        let b = Rational::new(i64::MAX - 3, 3);
+        assert_parts(a.abs_diff(&b), 1, 1);
+        assert_parts(b.abs_diff(&a), 1, 1);
+        assert!(std::panic::catch_unwind(|| a - b).is_err());
+        assert_parts(Rational::new(1, 3).abs_diff(&Rational::new(1, 2)), 1, 6);
+    }
+
+    #[test]
+    fn into_mixed_splits_whole_and_fraction() {
+        let m = Rational::new(-7, 2).into_mixed();
+        assert_eq!((m.whole, m.fraction), (-3, Rational::new(-1, 2)));
+        assert_eq!(m.to_string(), "-3 1/2");
+        assert_eq!(Rational::new(7, 2).into_mixed().to_string(), "3 1/2");
+        let m = Rational::new(8, 2).into_mixed();
+        assert_eq!((m.whole, m.fraction), (4, Rational::new(0, 1)));
+        assert_eq!(m.to_string(), "4");
+        assert_eq!(Rational::new(-1, 3).into_mixed().to_string(), "-1/3");
+        assert_eq!(Rational::new(0, 3).into_mixed().to_string(), "0");
+    }
+
+    #[test]
+    fn compare_algorithms_finds_identical_results() {
+        for &(v, e) in &[
+            (6.4285714285, 0.000000001),
+            (0.75, 0.0001),
+            (std::f64::consts::PI, 1e-6),
+            (1234.5678, 1e-5),
+        ] {
+            let c = Rational::compare_algorithms(v, e);
+            assert!(c.identical, "{:?}", c);
+            assert!(c.fast_from_steps <= c.from_steps);
+        }
+        let c = Rational::compare_algorithms(std::f64::consts::PI, 1e-6);
+        assert_eq!(c.from_steps, 26);
+    }
+
+    #[test]
+    fn farey_neighbors_and_mediant() {
+        let (a, b) = (Rational::new(1, 3), Rational::new(1, 2));
+        assert!(a.are_farey_neighbors(&b) && b.are_farey_neighbors(&a));
+        assert_parts(a.farey_mediant(&b).unwrap(), 2, 5);
+        let c = Rational::new(2, 3);
+        assert!(!a.are_farey_neighbors(&c));
+        assert!(a.farey_mediant(&c).is_none());
+    }
+
+    #[test]
+    fn percent_change_is_relative_to_self() {
+        assert_parts(Rational::new(3, 2).percent_change_to(&Rational::new(3, 1)), 1, 1);
+        assert_parts(Rational::new(3, 2).percent_change_to(&Rational::new(3, 4)), -1, 2);
+        assert_parts(
+            Rational::new(-2, 1).percent_change_to(&Rational::new(-1, 1)),
+            -1,
+            2,
+        );
+        assert!(
+            std::panic::catch_unwind(|| Rational::new(0, 1).percent_change_to(&Rational::new(1, 1))).is_err()
+        );
+    }
+
+    #[test]
+    fn nth_convergent_and_continued_fraction_round_trip() {
+        let r = Rational::new(355, 113);
+        assert_parts(r.nth_convergent(0), 3, 1);
+        assert_parts(r.nth_convergent(1), 22, 7);
+        assert_parts(r.nth_convergent(2), 355, 113);
+        assert_parts(r.nth_convergent(10), 355, 113);
+        assert_parts(Rational::from_continued_fraction(&[-4, 2]), -7, 2);
+        assert_parts(Rational::from_continued_fraction(&[]), 1, 0);
+        let mut g = Lcg(3);
+        for _ in 0..2000 {
+            let x = g.rat(100000);
+            assert_eq!(Rational::from_continued_fraction(&x.to_continued_fraction()), x);
+        }
+    }
+
+    #[test]
+    fn continued_fraction_round_trips_on_a_grid() {
+        for numer in -60..=60 {
+            for denom in 1..=60 {
+                let r = Rational::new(numer, denom);
+                assert_eq!(Rational::from_continued_fraction(&r.to_continued_fraction()), r);
+            }
+        }
+    }
+
+    #[test]
+    fn operators_return_normalized_results() {
+        let r = Rational::raw(-6, -8).normalized();
+        assert_parts(r, 3, 4);
+        let n = r.normalized();
+        assert_eq!((n.numer, n.denom), (n.normalized().numer, n.normalized().denom));
+        let mut g = Lcg(9);
+        for _ in 0..2000 {
+            let (a, b) = (g.rat(1000), g.rat(1000));
+            for x in [a + b, a - b, a * b, -a, -(-a), -&b] {
+                assert!(x.invariants_hold());
+                let y = x.normalized();
+                assert_eq!((x.numer, x.denom), (y.numer, y.denom));
+            }
+            if b.numer != 0 {
+                assert!((a / b).invariants_hold());
+            }
+        }
+    }
+
+    #[test]
+    fn sum_exact_survives_intermediate_overflow() {
+        let big = i64::MAX / 2;
+        let v: Vec<Rational> = (0..100)
+            .map(|i| Rational::new(if i % 2 == 0 { big } else { -big + 1 }, 7))
+            .collect();
+        assert_parts(Rational::sum_exact(&v).unwrap(), 50, 7);
+        assert!(std::panic::catch_unwind(|| v.iter().fold(Rational::new(0, 1), |a, b| a + *b)).is_err());
+        assert_parts(
+            Rational::sum_exact(&[Rational::new(1, 2), Rational::new(1, 3), Rational::new(1, 6)]).unwrap(),
+            1,
+            1,
+        );
+        assert_parts(Rational::sum_exact(&[]).unwrap(), 0, 1);
+        assert!(Rational::sum_exact(&[Rational::new(i64::MAX, 1), Rational::new(i64::MAX, 1)]).is_none());
+    }
+
+    #[test]
+    fn complement_in_unit_needs_a_proper_fraction() {
+        assert_parts(Rational::new(1, 3).complement_in_unit().unwrap(), 2, 3);
+        assert!(Rational::new(4, 3).complement_in_unit().is_none());
+        assert!(Rational::new(0, 3).complement_in_unit().is_none());
+        assert!(Rational::new(1, 1).complement_in_unit().is_none());
+        assert!(Rational::new(-1, 3).complement_in_unit().is_none());
+    }
+
+    #[test]
+    fn evaluate_linear_maps_each_input() {
+        let v = Rational::evaluate_linear(&Rational::new(1, 2), &Rational::new(1, 3), &[0, 1, 2]);
+        assert_eq!(pairs(&v), vec![(1, 3), (5, 6), (4, 3)]);
+    }
+
+    #[test]
+    fn round_with_covers_every_mode() {
+        use RoundingMode::*;
+        let modes = [HalfUp, HalfDown, HalfEven, TowardZero, AwayFromZero, Floor, Ceil];
+        let p: Vec<i64> = modes.iter().map(|&m| Rational::new(7, 2).round_with(m)).collect();
+        let n: Vec<i64> = modes
+            .iter()
+            .map(|&m| Rational::new(-7, 2).round_with(m))
+            .collect();
+        assert_eq!(p, vec![4, 3, 4, 3, 4, 3, 4]);
+        assert_eq!(n, vec![-3, -4, -4, -3, -4, -4, -3]);
+        assert_eq!(Rational::new(5, 2).round_with(HalfEven), 2);
+        assert_eq!(Rational::new(-5, 2).round_with(HalfEven), -2);
+        assert_eq!(Rational::new(-7, 3).round_with(HalfUp), -2);
+        assert_eq!(Rational::new(-8, 3).round_with(HalfDown), -3);
+        assert_eq!(Rational::new(-8, 3).round_with(TowardZero), -2);
+        assert_eq!(Rational::new(6, 3).round_with(Ceil), 2);
+        assert_eq!(
+            (Rational::new(-7, 3).floor(), Rational::new(-7, 3).ceil()),
+            (-3, -2)
+        );
+        assert_eq!((Rational::new(7, 3).floor(), Rational::new(7, 3).ceil()), (2, 3));
+        assert_eq!(
+            (Rational::new(-6, 3).floor(), Rational::new(-6, 3).ceil()),
+            (-2, -2)
+        );
+    }
+
+    #[test]
+    fn parallel_sums_reciprocals() {
+        assert_parts(
+            Rational::parallel(&[Rational::new(2, 1), Rational::new(2, 1)]).unwrap(),
+            1,
+            1,
+        );
+        assert_parts(
+            Rational::parallel(&[Rational::new(2, 1), Rational::new(3, 1), Rational::new(6, 1)]).unwrap(),
+            1,
+            1,
+        );
+        assert_parts(Rational::parallel(&[Rational::new(1, 2)]).unwrap(), 1, 2);
+        assert!(Rational::parallel(&[]).is_none());
+        assert!(Rational::parallel(&[Rational::new(2, 1), Rational::new(0, 1)]).is_none());
+        assert_parts(Rational::new(-2, 3).recip(), -3, 2);
+    }
+
+    #[test]
+    fn stern_brocot_bounds_and_subtree_iter() {
+        let r = Rational::new(1, 2);
+        let b = r.stern_brocot_bounds();
+        assert_parts(b.0, 0, 1);
+        assert_parts(b.1, 1, 1);
+        let b = Rational::new(1, 1).stern_brocot_bounds();
+        assert_parts(b.0, 0, 1);
+        assert_parts(b.1, 1, 0);
+        let b = Rational::new(3, 1).stern_brocot_bounds();
+        assert_parts(b.0, 2, 1);
+        assert_parts(b.1, 1, 0);
+        let b = Rational::new(2, 3).stern_brocot_bounds();
+        assert_parts(b.0, 1, 2);
+        assert_parts(b.1, 1, 1);
+        let b = Rational::new(355, 113).stern_brocot_bounds();
+        assert_eq!(b.0.mediant(&b.1), Rational::new(355, 113));
+        let v: Vec<Rational> = r.subtree_iter(3).collect();
+        assert_eq!(v.len(), 15);
+        assert_parts(v[0], 1, 2);
+        assert_parts(v[1], 1, 3);
+        assert_parts(v[2], 2, 3);
+        let all: Vec<Rational> = Rational::new(1, 1).subtree_iter(3).collect();
+        assert_eq!(
+            pairs(&all[..7]),
+            vec![(1, 1), (1, 2), (2, 1), (1, 3), (2, 3), (3, 2), (3, 1)]
+        );
+        for d in 0..6 {
+            assert_eq!(r.subtree_iter(d).count(), (1 << (d + 1)) - 1);
+        }
+    }
+
+    #[test]
+    fn prefers_fraction_compares_string_lengths() {
+        assert!(Rational::prefers_fraction(0.5, 1e-9));
+        assert!(Rational::prefers_fraction(-0.75, 1e-9));
+        assert!(Rational::prefers_fraction(1.0 / 3.0, 1e-9));
+        assert!(!Rational::prefers_fraction(0.37219, 1e-9));
+        assert!(!Rational::prefers_fraction(0.0, 1e-9));
+    }
+
+    #[test]
+    fn prefers_fraction_handles_large_values() {
+        // from would descend 12.58 billion single steps for this value
+        assert!(Rational::prefers_fraction(12_581_890_123.538_462, 1e-7));
+        assert!(!Rational::prefers_fraction(12_581_890_123.123_456, 1e-7));
+    }
+
+    #[test]
+    fn wrapping_ops_skip_reduction() {
+        let max = Rational::new(i64::MAX, 1);
+        let one = Rational::new(1, 1);
+        let r = max.wrapping_add(&one);
+        assert_eq!((r.numer, r.denom), (i64::MIN, 1));
+        let r = Rational::new(i64::MIN, 1).wrapping_sub(&one);
+        assert_eq!((r.numer, r.denom), (i64::MAX, 1));
+        let r = max.wrapping_mul(&Rational::new(2, 1));
+        assert_eq!((r.numer, r.denom), (-2, 1));
+        let r = Rational::new(1, 2).wrapping_add(&Rational::new(1, 2));
+        assert_eq!((r.numer, r.denom), (4, 4));
+        assert!(!r.invariants_hold());
+        let r = Rational::new(1, 1 << 32).wrapping_mul(&Rational::new(1, 1 << 32));
+        assert_eq!((r.numer, r.denom), (1, 0));
+        let r = one.wrapping_div(&Rational::new(-1, 2));
+        assert_eq!((r.numer, r.denom), (2, -1));
+    }
+
+    #[test]
+    fn common_denominator_is_the_lcm() {
+        let v = [Rational::new(1, 2), Rational::new(1, 3), Rational::new(1, 6)];
+        assert_eq!(Rational::common_denominator(&v), Some(6));
+        assert_eq!(
+            Rational::to_common_denominator(&v),
+            Some(vec![(3, 6), (2, 6), (1, 6)])
+        );
+        assert_eq!(
+            Rational::common_denominator(&[Rational::new(-3, 4), Rational::new(5, 1)]),
+            Some(4)
+        );
+        assert_eq!(Rational::common_denominator(&[]), Some(1));
+        assert_eq!(
+            Rational::common_denominator(&[Rational::new(1, i64::MAX), Rational::new(1, 2)]),
+            None
+        );
+        assert_eq!(
+            Rational::to_common_denominator(&[Rational::new(i64::MAX, 1), Rational::new(1, 2)]),
+            None
+        );
+    }
+
+    #[test]
+    fn log2_bracket_matches_float_log2() {
+        assert_eq!(Rational::new(3, 1).log2_bracket(), Some((1, 2)));
+        assert_eq!(Rational::new(1, 8).log2_bracket(), Some((-3, -2)));
+        assert_eq!(Rational::new(1, 1).log2_bracket(), Some((0, 1)));
+        assert_eq!(Rational::new(4, 1).log2_bracket(), Some((2, 3)));
+        assert_eq!(Rational::new(3, 4).log2_bracket(), Some((-1, 0)));
+        assert_eq!(Rational::new(7, 9).log2_bracket(), Some((-1, 0)));
+        assert_eq!(Rational::new(i64::MAX, 1).log2_bracket(), Some((62, 63)));
+        assert_eq!(Rational::new(1, i64::MAX).log2_bracket(), Some((-63, -62)));
+        assert_eq!(Rational::new(0, 1).log2_bracket(), None);
+        assert_eq!(Rational::new(-3, 1).log2_bracket(), None);
+        let mut g = Lcg(11);
+        for _ in 0..5000 {
+            let r = g.rat(1 << 40);
+            if r.numer > 0 {
+                let (k, _) = r.log2_bracket().unwrap();
+                assert_eq!(k, r.value().log2().floor() as i32, "{:?}", r);
+            }
+        }
+    }
+
+    #[test]
+    fn equivalent_fractions_scale_the_reduced_form() {
+        assert_eq!(
+            Rational::new(1, 2).equivalent_fractions(3),
+            vec![(1, 2), (2, 4), (3, 6)]
+        );
+        assert_eq!(
+            Rational::new(-2, 4).equivalent_fractions(2),
+            vec![(-1, 2), (-2, 4)]
+        );
+        assert!(Rational::new(1, 2).equivalent_fractions(0).is_empty());
+        assert_eq!(Rational::new(i64::MAX, 1).equivalent_fractions(2), vec![(i64::MAX, 1)]);
+        assert_eq!(Rational::new(1, i64::MAX / 2).equivalent_fractions(5).len(), 2);
+    }
+
+    #[test]
+    fn from_scaled_reads_fixed_point_values() {
+        assert_parts(Rational::from_scaled(125, 2).unwrap(), 5, 4);
+        assert_parts(Rational::from_scaled(-125, 2).unwrap(), -5, 4);
+        assert_parts(Rational::from_scaled(0, 5).unwrap(), 0, 1);
+        assert_parts(Rational::from_scaled(7, 0).unwrap(), 7, 1);
+        assert_parts(
+            Rational::from_scaled(5_000_000_000_000_000_000i128 as i64 / 1000, 19).unwrap(),
+            1,
+            2000,
+        );
+        assert_parts(Rational::from_scaled(0, 39).unwrap(), 0, 1);
+        assert_parts(
+            Rational::from_scaled(10_i64.pow(18), 36).unwrap(),
+            1,
+            10_i64.pow(18),
+        );
+        assert_eq!(Rational::from_scaled(1, 19), None);
+        assert_eq!(Rational::from_scaled(i64::MAX, 39), None);
+        assert_eq!(Rational::from_scaled(1, u32::MAX), None);
+    }
+
+    #[test]
+    fn harmonic_number_until_overflow() {
+        assert_parts(Rational::harmonic_number(1).unwrap(), 1, 1);
+        assert_parts(Rational::harmonic_number(4).unwrap(), 25, 12);
+        assert_parts(Rational::harmonic_number(20).unwrap(), 55835135, 15519504);
+        assert!(Rational::harmonic_number(46).is_some());
+        assert!(Rational::harmonic_number(100).is_none());
+        assert_parts(Rational::harmonic_number(0).unwrap(), 0, 1);
+    }
+
+    #[test]
+    fn sum_is_integer_uses_i128() {
+        assert!(Rational::new(1, 3).sum_is_integer(&Rational::new(2, 3)));
+        assert!(!Rational::new(1, 3).sum_is_integer(&Rational::new(1, 2)));
+        assert!(Rational::new(-1, 3).sum_is_integer(&Rational::new(7, 3)));
+        assert!(Rational::new(i64::MAX, 2).sum_is_integer(&Rational::new(1, 2)));
+    }
+
+    #[test]
+    fn unicode_str_parses_vulgar_fractions() {
+        assert_parts(Rational::from_unicode_str("½").unwrap(), 1, 2);
+        assert_parts(Rational::from_unicode_str("1¾").unwrap(), 7, 4);
+        assert_parts(Rational::from_unicode_str("-1¾").unwrap(), -7, 4);
+        assert_parts(Rational::from_unicode_str("2 ⅓").unwrap(), 7, 3);
+        assert_parts(Rational::from_unicode_str("-⅝").unwrap(), -5, 8);
+        assert_parts(Rational::from_unicode_str("3 1/2").unwrap(), 7, 2);
+        assert_eq!(
+            Rational::from_unicode_str("1⅟").unwrap_err(),
+            RationalError::InvalidFormat
+        );
+        assert_eq!(
+            Rational::from_unicode_str("x½").unwrap_err(),
+            RationalError::InvalidFormat
+        );
+        assert_eq!(
+            Rational::from_unicode_str("1-½").unwrap_err(),
+            RationalError::InvalidFormat
+        );
+        assert_eq!(
+            Rational::from_unicode_str("").unwrap_err(),
+            RationalError::InvalidFormat
+        );
+    }
+
+    #[test]
+    fn unicode_str_rejects_bad_signs() {
+        assert_eq!(
+            Rational::from_unicode_str("--1½").unwrap_err(),
+            RationalError::InvalidFormat
+        );
+        assert_eq!(
+            Rational::from_unicode_str("+1½").unwrap_err(),
+            RationalError::InvalidFormat
+        );
+        assert_parts(
+            Rational::from_unicode_str("10000000000½").unwrap(),
+            20000000001,
+            2,
+        );
+    }
+
+    #[test]
+    fn unicode_str_reports_overflow() {
+        assert_eq!(
+            Rational::from_unicode_str("9223372036854775807½"),
+            Err(RationalError::Overflow(OverflowDirection::Positive))
+        );
+        assert_eq!(
+            Rational::from_unicode_str("-9223372036854775807½"),
+            Err(RationalError::Overflow(OverflowDirection::Negative))
+        );
+        assert_parts(
+            Rational::from_unicode_str("-9223372036854775807↉").unwrap(),
+            -9223372036854775807,
+            1,
+        );
+        assert_parts(
+            Rational::from_unicode_str("4611686018427387903½").unwrap(),
+            9223372036854775807,
+            2,
+        );
+    }
+
+    #[test]
+    fn stern_brocot_index_is_breadth_first() {
+        let idx: Vec<Option<u64>> = [
+            (1, 1),
+            (1, 2),
+            (2, 1),
+            (1, 3),
+            (2, 3),
+            (3, 2),
+            (3, 1),
+            (1, 4),
+            (2, 5),
+            (3, 5),
+            (3, 4),
+        ]
+        .iter()
+        .map(|&(n, d)| Rational::new(n, d).stern_brocot_index())
+        .collect();
+        assert_eq!(idx, (1..=11).map(Some).collect::<Vec<_>>());
+        let all: Vec<Rational> = Rational::new(1, 1).subtree_iter(5).collect();
+        for (i, r) in all.iter().enumerate() {
+            assert_eq!(r.stern_brocot_index(), Some(i as u64 + 1));
+        }
+        assert_eq!(Rational::new(0, 1).stern_brocot_index(), None);
+        assert_eq!(Rational::new(-1, 2).stern_brocot_index(), None);
+        assert_eq!(Rational::new(1, 64).stern_brocot_index(), Some(1 << 63));
+        assert_eq!(Rational::new(1, 65).stern_brocot_index(), None);
+        assert_eq!(Rational::new(64, 1).stern_brocot_index(), Some(u64::MAX));
+    }
+
+    #[test]
+    fn simplest_from_takes_the_simplest_fraction_in_tolerance() {
+        assert_parts(Rational::from(0.3, 0.35), 1, 2);
+        assert_parts(Rational::simplest_from(0.3, 0.35), 0, 1);
+        assert_parts(Rational::simplest_from(std::f64::consts::PI, 1e-6), 355, 113);
+        assert_parts(Rational::simplest_from(0.75, 1e-4), 3, 4);
+    }
+
+    #[test]
+    fn simplest_from_matches_from_for_large_values() {
+        for v in [91022.52773785726, 23631.48193248854] {
+            let r = Rational::simplest_from(v, 1e-9);
+            assert_eq!((r.numer, r.denom), {
+                let f = Rational::from(v, 1e-9);
+                (f.numer, f.denom)
+            });
+        }
+    }
+
+    #[test]
+    fn decimal_period_of_fractions() {
+        assert_eq!(Rational::new(1, 3).decimal_period(), 1);
+        assert_eq!(Rational::new(1, 7).decimal_period(), 6);
+        assert_eq!(Rational::new(1, 4).decimal_period(), 0);
+        assert_eq!(Rational::new(5, 1).decimal_period(), 0);
+        assert_eq!(Rational::new(1, 12).decimal_period(), 1);
+        assert_eq!(Rational::new(-3, 14).decimal_period(), 6);
+        assert_eq!(Rational::new(1, 97).decimal_period(), 96);
+    }
+
+    #[test]
+    fn multiplicative_order_matches_naive_search() {
+        fn naive(base: i64, m: i64) -> u64 {
+            let mut p = base % m;
+            let mut k = 1;
+            while p != 1 {
+                p = p * base % m;
+                k += 1;
+            }
+            k
+        }
+        for m in 2..3000i64 {
+            for b in [2i64, 3, 10] {
+                if Rational::gcd(b, m) == 1 {
+                    assert_eq!(Rational::multiplicative_order(b, m), naive(b, m), "{} {}", b, m);
+                }
+            }
+        }
+        assert_eq!(Rational::new(1, 1_000_000_007).decimal_period(), 1_000_000_006);
+        assert_eq!(
+            Rational::new(1, 9223372036854775783).decimal_period(),
+            9223372036854775782
+        );
+        let p = 9223372036854775783u64;
+        assert!(Rational::is_prime(p));
+        let period = Rational::new(1, p as i64).decimal_period();
+        assert_eq!((p - 1) % period, 0);
+        assert_eq!(Rational::pow_mod(10, period, p), 1);
+        assert_eq!(
+            Rational::prime_factors(600851475143),
+            vec![(71, 1), (839, 1), (1471, 1), (6857, 1)]
+        );
+        assert_eq!(Rational::prime_factors(1 << 62), vec![(2, 62)]);
+        assert_eq!(
+            Rational::prime_factors(4611686014132420609),
+            vec![(2147483647, 2)]
+        );
+    }
+
+    #[test]
+    fn new_raw_keeps_the_written_form() {
+        let r = Rational::new_raw(2, 4).unwrap();
+        assert_eq!((r.numer, r.denom), (2, 4));
+        assert!(!r.invariants_hold());
+        assert_eq!(r, Rational::new(1, 2));
+        assert_eq!(r.cmp(&Rational::new(1, 2)), Ordering::Equal);
+        assert!(r < Rational::new(2, 3));
+        let r = Rational::new_raw(3, -6).unwrap();
+        assert_eq!((r.numer, r.denom), (-3, 6));
+        assert_eq!(r, Rational::new(-1, 2));
+        assert_eq!(
+            Rational::new_raw(1, 0).unwrap_err(),
+            RationalError::ZeroDenominator
+        );
+        assert_eq!(
+            Rational::new_raw(i64::MIN, -1).unwrap_err(),
+            RationalError::Overflow(OverflowDirection::Positive)
+        );
+        assert_eq!(
+            Rational::new_raw(1, i64::MIN).unwrap_err(),
+            RationalError::Overflow(OverflowDirection::Negative)
+        );
+        assert_eq!(r.normalized().numer, -1);
+    }
+
+    #[test]
+    fn lerp_is_exact() {
+        let r = |n, d| Rational::new(n, d);
+        assert_parts(r(0, 1).lerp(&r(1, 1), &r(1, 4)), 1, 4);
+        assert_parts(r(1, 3).lerp(&r(2, 3), &r(1, 2)), 1, 2);
+        assert_parts(r(-1, 2).lerp(&r(5, 7), &r(0, 1)), -1, 2);
+        assert_parts(r(-1, 2).lerp(&r(5, 7), &r(1, 1)), 5, 7);
+        let big = r(i64::MAX, 3);
+        assert_parts(big.lerp(&r(i64::MAX - 6, 3), &r(1, 2)), i64::MAX - 3, 3);
+        let mut g = Lcg(5);
+        for _ in 0..2000 {
+            let (a, b, t) = (g.rat(1000), g.rat(1000), g.rat(1000));
+            assert_eq!(a.lerp(&b, &t), a + (b - a) * t);
+        }
+    }
+
+    #[test]
+    fn le_bytes_round_trip() {
+        let mut g = Lcg(13);
+        for _ in 0..2000 {
+            let r = g.rat(i64::MAX / 2);
+            let b = Rational::from_le_bytes(r.to_le_bytes()).unwrap();
+            assert_eq!((b.numer, b.denom), (r.numer, r.denom));
+        }
+        let mut bytes = [0u8; 16];
+        bytes[0] = 5;
+        assert_eq!(
+            Rational::from_le_bytes(bytes).unwrap_err(),
+            RationalError::ZeroDenominator
+        );
+        let mut bytes = [0u8; 16];
+        bytes[0] = 6;
+        bytes[8] = 0xfc;
+        for b in &mut bytes[9..] {
+            *b = 0xff;
+        }
+        let r = Rational::from_le_bytes(bytes).unwrap();
+        assert_parts(r, -3, 2);
+        assert_eq!(
+            Rational::new(1, 2).to_le_bytes()[..9],
+            [1, 0, 0, 0, 0, 0, 0, 0, 2]
+        );
+    }
+
+    #[test]
+    fn refine_never_moves_away_from_the_target() {
+        let pi = std::f64::consts::PI;
+        let mut r = Rational::new(3, 1);
+        let mut err = (r.value() - pi).abs();
+        for _ in 0..30 {
+            r = r.refine(pi, 3);
+            let e = (r.value() - pi).abs();
+            assert!(e <= err);
+            err = e;
+        }
+        assert!(err < 1e-6, "{:?}", r);
+        assert_parts(Rational::new(3, 1).refine(pi, 0), 3, 1);
+        assert_parts(Rational::new(3, 1).refine(pi, 1), 22, 7);
+        assert_parts(Rational::new(3, 1).refine(pi, 3), 355, 113);
+        assert_parts(Rational::new(1, 2).refine(0.5, 10), 1, 2);
+        assert_parts(Rational::new(1, 1).refine(0.75, 10), 3, 4);
+        assert_parts(Rational::new(1, 2).refine(3.0, 1), 2, 1);
+        assert_parts(Rational::new(1, 2).refine(3.0, 3), 3, 1);
+        assert_parts(Rational::new(1, 2).refine(1.05, 1), 1, 1);
+        let sqrt_2 = std::f64::consts::SQRT_2;
+        let mut r = Rational::new(5, 1);
+        for _ in 0..5 {
+            let n = r.refine(sqrt_2, 1);
+            assert!((n.value() - sqrt_2).abs() < (r.value() - sqrt_2).abs());
+            r = n;
+        }
+    }
+
+    #[test]
+    fn refine_jumps_long_runs_in_one_step() {
+        let r = Rational::new(3, 1).refine(3.0 + 1e-9, 1);
+        assert!(r.denom > 1 && (r.value() - 3.0).abs() < 1e-8, "{:?}", r);
+        assert_parts(Rational::new(1, 1).refine(0.0, 5), 1, i64::MAX);
+    }
+
+    #[test]
+    fn refine_restarts_for_non_positive_values() {
+        assert_parts(Rational::new(0, 1).refine(0.3, 1), 1, 3);
+        assert_parts(Rational::new(0, 1).refine(0.3, 2), 2, 7);
+        assert_parts(Rational::new(-1, 2).refine(0.3, 3), 3, 10);
+    }
+
+    #[test]
+    fn cmp_by_simplicity_orders_by_denominator() {
+        let mut v = vec![Rational::new(22, 7), Rational::new(3, 1), Rational::new(355, 113)];
+        v.sort_by(|a, b| a.cmp_by_simplicity(b));
+        assert_eq!(pairs(&v), vec![(3, 1), (22, 7), (355, 113)]);
+        assert_eq!(
+            Rational::new(-1, 2).cmp_by_simplicity(&Rational::new(3, 2)),
+            Ordering::Less
+        );
+        assert_eq!(
+            Rational::new(-1, 2).cmp_by_simplicity(&Rational::new(1, 2)),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn mean_avoids_intermediate_overflow() {
+        assert_parts(
+            Rational::mean(&[Rational::new(1, 2), Rational::new(1, 3), Rational::new(1, 6)]).unwrap(),
+            1,
+            3,
+        );
+        assert!(Rational::mean(&[]).is_none());
+        assert_parts(
+            Rational::mean(&[Rational::new(i64::MAX, 1), Rational::new(i64::MAX - 2, 1)]).unwrap(),
+            i64::MAX - 1,
+            1,
+        );
+        assert_parts(Rational::mean(&[Rational::new(-3, 4)]).unwrap(), -3, 4);
+    }
+
+    #[test]
+    fn product_and_quotient_signs_match_floats() {
+        let vals = [
+            Rational::new(-3, 2),
+            Rational::new(0, 1),
+            Rational::new(5, 7),
+            Rational::new(i64::MAX, 1),
+            Rational::new(i64::MIN + 1, 3),
+        ];
+        for a in vals {
+            for b in vals {
+                assert_eq!(
+                    a.sign_of_product(&b),
+                    (a.value() * b.value()).partial_cmp(&0.0).unwrap() as i64
+                );
+                if b.numer != 0 {
+                    assert_eq!(
+                        a.sign_of_quotient(&b),
+                        (a.value() / b.value()).partial_cmp(&0.0).unwrap() as i64
+                    );
+                }
+            }
+        }
+        assert!(
+            std::panic::catch_unwind(|| Rational::new(1, 2).sign_of_quotient(&Rational::new(0, 1))).is_err()
+        );
+    }
+
+    #[test]
+    fn from_f64_exact_and_limit_denominator() {
+        let pi = Rational::from_f64_exact(std::f64::consts::PI).unwrap();
+        assert_parts(pi, 884279719003555, 281474976710656);
+        assert_parts(pi.limit_denominator(100), 311, 99);
+        assert_parts(pi.limit_denominator(10), 22, 7);
+        assert_parts(pi.limit_denominator(1000), 355, 113);
+        assert_parts(pi.limit_denominator(1), 3, 1);
+        assert_parts(
+            Rational::from_f64_exact(0.1).unwrap().limit_denominator(1000000),
+            1,
+            10,
+        );
+        assert_parts(Rational::from_f64_exact(-0.75).unwrap(), -3, 4);
+        assert_parts(Rational::from_f64_exact(0.0).unwrap(), 0, 1);
+        assert_parts(Rational::from_f64_exact(1e18).unwrap(), 1000000000000000000, 1);
+        assert!(Rational::from_f64_exact(1e19).is_none());
+        assert!(Rational::from_f64_exact(1e-30).is_none());
+        assert!(Rational::from_f64_exact(f64::NAN).is_none());
+        assert_parts(Rational::from_f64_exact(2f64.powi(-62)).unwrap(), 1, 1 << 62);
+        assert!(Rational::from_f64_exact(2f64.powi(-63)).is_none());
+        assert_parts(
+            Rational::new(-884279719003555, 281474976710656).limit_denominator(100),
+            -311,
+            99,
+        );
+        assert_parts(Rational::new(7, 3).limit_denominator(5), 7, 3);
+    }
+
+    #[test]
+    fn divides_means_an_integer_quotient() {
+        assert!(Rational::new(1, 2).divides(&Rational::new(3, 2)));
+        assert!(!Rational::new(1, 3).divides(&Rational::new(1, 2)));
+        assert!(Rational::new(-1, 4).divides(&Rational::new(3, 2)));
+        assert!(!Rational::new(0, 1).divides(&Rational::new(0, 1)));
+        assert!(Rational::new(2, 3).divides(&Rational::new(0, 1)));
+    }
+
+    #[test]
+    fn eval_polynomial_low_order_first() {
+        let c = [Rational::new(1, 1), Rational::new(2, 1), Rational::new(3, 1)];
+        assert_parts(Rational::eval_polynomial(&c, &Rational::new(1, 2)), 11, 4);
+        assert_parts(Rational::eval_polynomial(&[], &Rational::new(1, 2)), 0, 1);
+        assert_parts(Rational::eval_polynomial(&c, &Rational::new(-2, 3)), 1, 1);
+    }
+
+    #[test]
+    fn nearest_unit_fraction_in_the_unit_interval() {
+        assert_parts(Rational::new(2, 5).nearest_unit_fraction().unwrap(), 1, 3);
+        assert_parts(Rational::new(1, 1).nearest_unit_fraction().unwrap(), 1, 1);
+        assert_parts(Rational::new(1, 7).nearest_unit_fraction().unwrap(), 1, 7);
+        assert_parts(Rational::new(3, 4).nearest_unit_fraction().unwrap(), 1, 1);
+        assert_parts(Rational::new(5, 12).nearest_unit_fraction().unwrap(), 1, 2);
+        assert_parts(Rational::new(1, i64::MAX).nearest_unit_fraction().unwrap(), 1, i64::MAX);
+        assert_parts(Rational::new(2, i64::MAX).nearest_unit_fraction().unwrap(), 1, i64::MAX / 2 + 1);
+        let d = (1 << 40) + 1;
+        assert_parts(Rational::new(3, d).nearest_unit_fraction().unwrap(), 1, d / 3 + 1);
+        assert_eq!(
+            Rational::new(0, 1).nearest_unit_fraction().unwrap_err(),
+            RationalError::OutOfRange
+        );
+        assert_eq!(
+            Rational::new(3, 2).nearest_unit_fraction().unwrap_err(),
+            RationalError::OutOfRange
+        );
+        assert_eq!(
+            Rational::new(-1, 2).nearest_unit_fraction().unwrap_err(),
+            RationalError::OutOfRange
+        );
+    }
+
+    #[test]
+    fn classify_covers_every_kind() {
+        use FractionKind::*;
+        let k: Vec<FractionKind> = [
+            (0, 1),
+            (2, 3),
+            (-2, 3),
+            (7, 2),
+            (4, 1),
+            (1, 5),
+            (-7, 2),
+            (-1, 5),
+            (1, 1),
+            (-4, 1),
+        ]
+        .iter()
+        .map(|&(n, d)| Rational::new(n, d).classify())
+        .collect();
+        assert_eq!(
+            k,
+            vec![
+                Zero,
+                ProperPositive,
+                ProperNegative,
+                Improper,
+                Integer,
+                Unit,
+                Improper,
+                ProperNegative,
+                Integer,
+                Integer
+            ]
+        );
+    }
+
+    #[test]
+    fn minkowski_question_mark_on_simple_fractions() {
+        let q = |n, d| Rational::new(n, d).minkowski_question_mark();
+        assert_eq!(q(1, 2), 0.5);
+        assert_eq!(q(1, 3), 0.25);
+        assert_eq!(q(2, 3), 0.75);
+        assert_eq!(q(0, 1), 0.0);
+        assert_eq!(q(1, 1), 1.0);
+        assert_eq!(q(2, 5), 0.375);
+        assert_eq!(q(3, 5), 0.625);
+        assert_eq!(q(1, 4), 0.125);
+    }
+
+    #[test]
+    fn compare_detailed_reports_the_signed_gap() {
+        assert_eq!(
+            Rational::new(1, 2).compare_detailed(&Rational::new(1, 3)),
+            (Ordering::Greater, 1)
+        );
+        assert_eq!(
+            Rational::new(1, 3).compare_detailed(&Rational::new(1, 2)),
+            (Ordering::Less, -1)
+        );
+        assert_eq!(
+            Rational::new(2, 4).compare_detailed(&Rational::new(1, 2)),
+            (Ordering::Equal, 0)
+        );
+        let (o, d) = Rational::new(i64::MAX, 1).compare_detailed(&Rational::new(i64::MIN + 1, 1));
+        assert_eq!((o, d), (Ordering::Greater, 2 * i64::MAX as i128));
+        let mut g = Lcg(17);
+        for _ in 0..2000 {
+            let (a, b) = (g.rat(1 << 40), g.rat(1 << 40));
+            let (o, d) = a.compare_detailed(&b);
+            assert_eq!(o, d.cmp(&0));
+            assert_eq!(o, a.value().partial_cmp(&b.value()).unwrap().then(o));
+        }
+    }
+
+    #[test]
+    fn denominator_divisors_are_sorted() {
+        assert_eq!(
+            Rational::new(5, 12).denominator_divisors(),
+            vec![1, 2, 3, 4, 6, 12]
+        );
+        assert_eq!(Rational::new(5, 1).denominator_divisors(), vec![1]);
+        assert_eq!(Rational::new(1, 9).denominator_divisors(), vec![1, 3, 9]);
+        assert_eq!(Rational::new(1, 97).denominator_divisors(), vec![1, 97]);
+        assert_eq!(Rational::new(1, 9223372036854775783).denominator_divisors(), vec![1, 9223372036854775783]);
+        assert_eq!(Rational::new(1, 1 << 62).denominator_divisors().len(), 63);
+        assert_eq!(Rational::new(1, 720).denominator_divisors().len(), 30);
+    }
+
+    #[test]
+    fn random_in_range_stays_in_bounds() {
+        let mut g = Lcg(2024);
+        let mut bit = || g.next().is_multiple_of(2);
+        let (lo, hi) = (Rational::new(1, 3), Rational::new(3, 4));
+        let mut seen = std::collections::BTreeSet::new();
+        for _ in 0..500 {
+            let r = Rational::random_in_range(&lo, &hi, &mut bit, 50).unwrap();
+            assert!(lo <= r && r <= hi && r.denom <= 50, "{:?}", r);
+            seen.insert(OrderedRational(r));
+        }
+        assert!(seen.len() > 20);
+        assert!(Rational::random_in_range(
+            &Rational::new(1, 1000),
+            &Rational::new(1, 999),
+            &mut || true,
+            100
+        )
+        .is_none());
+        assert_parts(
+            Rational::random_in_range(&Rational::new(2, 1), &Rational::new(2, 1), &mut bit, 100).unwrap(),
+            2,
+            1,
+        );
+    }
+
+    #[test]
+    fn random_in_range_stops_at_the_first_node_half_the_time() {
+        let mut g = Lcg(160);
+        let mut bit = || g.next().is_multiple_of(2);
+        let (lo, hi) = (Rational::new(1, 3), Rational::new(1, 2));
+        let mut counts = std::collections::BTreeMap::new();
+        for _ in 0..2000 {
+            let r = Rational::random_in_range(&lo, &hi, &mut bit, 50).unwrap();
+            assert!(lo <= r && r <= hi && r.denom <= 50);
+            *counts.entry(OrderedRational(r)).or_insert(0) += 1;
+        }
+        let half = counts[&OrderedRational(Rational::new(1, 2))];
+        assert!(half > 850 && half < 1150, "{}", half);
+        assert!(counts.len() > 40, "{}", counts.len());
+    }
+
+    #[test]
+    fn pi_approximation_improves_to_f64_precision() {
+        assert_parts(Rational::pi_approximation(1).0, 3, 1);
+        assert_parts(Rational::pi_approximation(2).0, 22, 7);
+        assert_parts(Rational::pi_approximation(4).0, 355, 113);
+        assert_parts(
+            Rational::pi_approximation(33).0,
+            2646693125139304345,
+            842468587426513207,
+        );
+        let errors: Vec<f64> = (1..=33).map(|n| Rational::pi_approximation(n).1).collect();
+        assert!(errors[..13].windows(2).all(|w| w[1] < w[0]));
+        assert!(errors[13..].iter().all(|&e| e <= 4.5e-16));
+    }
+
+    #[test]
+    #[should_panic]
+    fn pi_approximation_panics_past_33_terms() {
+        Rational::pi_approximation(34);
+    }
+
+    #[test]
+    fn continued_fraction_satisfies_checks_every_term() {
+        assert_eq!(Rational::new(415, 93).to_continued_fraction(), vec![4, 2, 6, 7]);
+        assert!(Rational::new(415, 93).continued_fraction_satisfies(|a| a <= 7));
+        assert!(!Rational::new(415, 93).continued_fraction_satisfies(|a| a <= 6));
+        assert!(!Rational::new(355, 113).continued_fraction_satisfies(|a| a < 10));
+    }
+
+    #[test]
+    fn wrap_unit_keeps_the_fractional_part() {
+        assert_parts(Rational::new(7, 3).wrap_unit(), 1, 3);
+        assert_parts(Rational::new(-1, 3).wrap_unit(), 2, 3);
+        assert_parts(Rational::new(-7, 3).wrap_unit(), 2, 3);
+        assert_parts(Rational::new(-4, 1).wrap_unit(), 0, 1);
+        assert_parts(Rational::new(5, 1).wrap_unit(), 0, 1);
+        assert_parts(Rational::new(i64::MIN + 1, 2).wrap_unit(), 1, 2);
+        for (a, b) in [(7, 3), (-7, 3), (-1, 9), (22, 7), (-100, 1)] {
+            let r = Rational::new(a, b);
+            assert_eq!(r.wrap_unit() + Rational::new(r.floor(), 1), r);
+        }
+    }
+
+    #[test]
+    fn is_between_is_inclusive_in_either_order() {
+        let (lo, hi) = (Rational::new(1, 3), Rational::new(1, 2));
+        assert!(Rational::new(2, 5).is_between(&lo, &hi));
+        assert!(Rational::new(1, 3).is_between(&lo, &hi));
+        assert!(Rational::new(2, 4).is_between(&lo, &hi));
+        assert!(!Rational::new(3, 10).is_between(&lo, &hi));
+        assert!(!Rational::new(5, 9).is_between(&lo, &hi));
+        assert!(Rational::new(2, 5).is_between(&hi, &lo));
+        assert!(!Rational::new(5, 9).is_between(&hi, &lo));
+        assert!(Rational::new(-1, 2).is_between(&Rational::new(-1, 2), &Rational::new(-1, 2)));
+    }
+
+    #[test]
+    fn weighted_mediant_generalizes_mediant() {
+        for (a, b) in [
+            ((1, 2), (2, 3)),
+            ((0, 1), (1, 0)),
+            ((-3, 4), (5, 7)),
+            ((1, 3), (1, 3)),
+        ] {
+            let (a, b) = (Rational::new(a.0, a.1), Rational::new(b.0, b.1));
+            let (w, m) = (Rational::weighted_mediant(&a, &b, 1, 1), a.mediant(&b));
+            assert_eq!((w.numer, w.denom), (m.numer, m.denom));
+        }
+        assert_parts(
+            Rational::weighted_mediant(&Rational::new(0, 1), &Rational::new(1, 1), 2, 1),
+            1,
+            3,
+        );
+        assert_parts(
+            Rational::weighted_mediant(&Rational::new(1, 2), &Rational::new(1, 3), 0, 5),
+            1,
+            3,
+        );
+        assert_parts(
+            Rational::weighted_mediant(&Rational::new(i64::MAX, 1), &Rational::new(-i64::MAX, 1), 3, 3),
+            0,
+            1,
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn weighted_mediant_panics_on_zero_weights() {
+        Rational::weighted_mediant(&Rational::new(1, 2), &Rational::new(1, 3), 0, 0);
+    }
+
+    #[test]
+    fn doubling_map_period_of_fractions() {
+        assert_eq!(Rational::new(1, 3).doubling_map_period(), Some(2));
+        assert_eq!(Rational::new(1, 7).doubling_map_period(), Some(3));
+        assert_eq!(Rational::new(1, 4).doubling_map_period(), None);
+        assert_eq!(Rational::new(0, 1).doubling_map_period(), None);
+        assert_eq!(Rational::new(5, 12).doubling_map_period(), Some(2));
+        assert_eq!(Rational::new(1, 11).doubling_map_period(), Some(10));
+        assert_eq!(Rational::new(-8, 7).doubling_map_period(), Some(3));
+        assert!(Rational::new(1, 3_037_000_493 * 3_037_000_453).doubling_map_period().is_some());
+    }
+
+    #[test]
+    fn farey_next_and_prev_walk_the_sequence() {
+        let f5 = [
+            (0, 1),
+            (1, 5),
+            (1, 4),
+            (1, 3),
+            (2, 5),
+            (1, 2),
+            (3, 5),
+            (2, 3),
+            (3, 4),
+            (4, 5),
+            (1, 1),
+        ];
+        let mut r = Rational::new(0, 1);
+        for w in f5.windows(2) {
+            assert_parts(r, w[0].0, w[0].1);
+            r = r.farey_next(5).unwrap();
+            assert_parts(r, w[1].0, w[1].1);
+        }
+        assert!(r.farey_next(5).is_none());
+        for w in f5.windows(2).rev() {
+            assert_parts(r, w[1].0, w[1].1);
+            r = r.farey_prev(5).unwrap();
+            assert_parts(r, w[0].0, w[0].1);
+        }
+        assert!(r.farey_prev(5).is_none());
+        assert!(Rational::new(1, 6).farey_next(5).is_none());
+        assert!(Rational::new(3, 2).farey_prev(5).is_none());
+        assert_parts(Rational::new(0, 1).farey_next(i64::MAX).unwrap(), 1, i64::MAX);
+        let big = Rational::new(1, 1).farey_prev(i64::MAX).unwrap();
+        assert_parts(big, i64::MAX - 1, i64::MAX);
+        for n in 1..40 {
+            let mut x = Rational::new(0, 1);
+            let mut count = 1;
+            while let Some(y) = x.farey_next(n) {
+                assert!(x.are_farey_neighbors(&y) && y > x);
+                assert_eq!(y.farey_prev(n), Some(x));
+                x = y;
+                count += 1;
+            }
+            let expected = 1
+                + (1..=n)
+                    .map(|q| (1..=q).filter(|&p| Rational::gcd(p, q) == 1).count())
+                    .sum::<usize>();
+            assert_eq!(count, expected);
+        }
+    }
+
+    #[test]
+    fn tangent_half_angle_of_pythagorean_slopes() {
+        let slope = Rational::from_slope_angle(6, 8).unwrap();
+        assert_parts(slope, 3, 4);
+        assert_parts(slope.slope_to_tangent_halfangle().unwrap(), 1, 3);
+        assert_parts(
+            Rational::from_slope_angle(4, 3)
+                .unwrap()
+                .slope_to_tangent_halfangle()
+                .unwrap(),
+            1,
+            2,
+        );
+        assert_parts(
+            Rational::from_slope_angle(-3, 4)
+                .unwrap()
+                .slope_to_tangent_halfangle()
+                .unwrap(),
+            -1,
+            3,
+        );
+        assert_parts(
+            Rational::from_slope_angle(5, 12)
+                .unwrap()
+                .slope_to_tangent_halfangle()
+                .unwrap(),
+            1,
+            5,
+        );
+        assert_parts(
+            Rational::from_slope_angle(0, 7)
+                .unwrap()
+                .slope_to_tangent_halfangle()
+                .unwrap(),
+            0,
+            1,
+        );
+        assert!(Rational::from_slope_angle(1, 1)
+            .unwrap()
+            .slope_to_tangent_halfangle()
+            .is_none());
+        assert!(Rational::from_slope_angle(1, 2)
+            .unwrap()
+            .slope_to_tangent_halfangle()
+            .is_none());
+        let t = Rational::from_slope_angle(3, 4)
+            .unwrap()
+            .slope_to_tangent_halfangle()
+            .unwrap();
+        assert_eq!(
+            Rational::new(2, 1) * t / (Rational::new(1, 1) - t * t),
+            Rational::new(3, 4)
+        );
+        assert_eq!(Rational::from_slope_angle(3, 0).unwrap_err(), RationalError::ZeroDenominator);
+        assert_eq!(Rational::from_slope_angle(0, 0).unwrap_err(), RationalError::ZeroDenominator);
+        assert_parts(Rational::from_slope_angle(i64::MIN, i64::MIN).unwrap(), 1, 1);
+        assert_eq!(
+            Rational::from_slope_angle(i64::MIN, -1).unwrap_err(),
+            RationalError::Overflow(OverflowDirection::Positive)
+        );
+    }
+
+    #[test]
+    fn from_closest_is_never_worse_than_from() {
+        assert_parts(Rational::from(0.3, 0.1), 1, 3);
+        assert_parts(Rational::from_closest(0.3, 0.1), 3, 10);
+        let pi = std::f64::consts::PI;
+        for e in [0.1, 0.01, 1e-3, 1e-4, 1e-6] {
+            let (f, c) = (Rational::from(pi, e), Rational::from_closest(pi, e));
+            assert!((c.value() - pi).abs() <= (f.value() - pi).abs());
+            assert!((c.value() - pi).abs() <= e);
+        }
+        assert_parts(Rational::from_closest(2.0, 0.1), 2, 1);
+        assert_parts(Rational::from_closest(0.5, 0.01), 1, 2);
+        let mut g = Lcg(7);
+        for _ in 0..300 {
+            let v = (g.next() % 100000) as f64 / 997.0;
+            let e = 1.0 / (1 + g.next() % 5000) as f64;
+            let (f, c) = (Rational::from(v, e), Rational::from_closest(v, e));
+            assert!((c.value() - v).abs() <= (f.value() - v).abs() && (c.value() - v).abs() <= e);
+        }
+    }
+
+    #[test]
+    fn geometric_sum_matches_repeated_addition() {
+        let one = Rational::new(1, 1);
+        assert_parts(
+            Rational::geometric_sum(&one, &Rational::new(1, 2), 3).unwrap(),
+            7,
+            4,
+        );
+        assert_parts(
+            Rational::geometric_sum(&Rational::new(2, 3), &one, 5).unwrap(),
+            10,
+            3,
+        );
+        assert_parts(
+            Rational::geometric_sum(&one, &Rational::new(1, 2), 0).unwrap(),
+            0,
+            1,
+        );
+        assert_parts(
+            Rational::geometric_sum(&Rational::new(3, 1), &Rational::new(-1, 1), 3).unwrap(),
+            3,
+            1,
+        );
+        assert_parts(
+            Rational::geometric_sum(&Rational::new(3, 1), &Rational::new(-1, 1), 4).unwrap(),
+            0,
+            1,
+        );
+        assert_parts(
+            Rational::geometric_sum(&one, &Rational::new(2, 1), 10).unwrap(),
+            1023,
+            1,
+        );
+        assert_parts(
+            Rational::geometric_sum(&one, &Rational::new(0, 1), 5).unwrap(),
+            1,
+            1,
+        );
+        for (a, r, n) in [((1, 3), (2, 5), 6), ((-7, 2), (-3, 4), 5), ((5, 1), (3, 2), 4)] {
+            let (a, r) = (Rational::new(a.0, a.1), Rational::new(r.0, r.1));
+            let mut expected = Rational::new(0, 1);
+            let mut term = a;
+            for _ in 0..n {
+                expected = expected + term;
+                term = term * r;
+            }
+            assert_eq!(Rational::geometric_sum(&a, &r, n).unwrap(), expected);
+        }
+        assert!(Rational::geometric_sum(&one, &Rational::new(1, 3), 200).is_none());
+    }
+
+    #[test]
+    fn det2x2_uses_i128_intermediates() {
+        let r = |n, d| Rational::new(n, d);
+        assert_parts(Rational::det2x2(&r(1, 2), &r(1, 3), &r(3, 2), &r(1, 1)), 0, 1);
+        assert_parts(Rational::det2x2(&r(1, 2), &r(1, 3), &r(1, 4), &r(1, 5)), 1, 60);
+        assert_parts(Rational::det2x2(&r(2, 1), &r(0, 1), &r(0, 1), &r(3, 1)), 6, 1);
+        assert_parts(
+            Rational::det2x2(&r(i64::MAX, 3), &r(i64::MAX, 5), &r(3, i64::MAX), &r(5, i64::MAX)),
+            16,
+            15,
+        );
+        assert_parts(
+            Rational::det2x2(&r(i64::MAX, 1), &r(1, 1), &r(1, 1), &r(1, i64::MAX)),
+            0,
+            1,
+        );
+    }
+
+    #[test]
+    fn solve_linear_finds_the_root() {
+        let r = |n, d| Rational::new(n, d);
+        assert_parts(Rational::solve_linear(&r(2, 3), &r(1, 2)).unwrap(), -3, 4);
+        assert_parts(Rational::solve_linear(&r(-5, 1), &r(10, 1)).unwrap(), 2, 1);
+        assert_parts(Rational::solve_linear(&r(7, 9), &r(0, 1)).unwrap(), 0, 1);
+        assert!(Rational::solve_linear(&r(0, 1), &r(1, 2)).is_none());
+        assert!(Rational::solve_linear(&r(0, 1), &r(0, 1)).is_none());
+        assert_parts(
+            Rational::solve_linear(&r(1, 1), &r(i64::MIN, 1)).unwrap_or(r(0, 1)),
+            0,
+            1,
+        );
+        let (a, b) = (r(-3, 7), r(11, 13));
+        let x = Rational::solve_linear(&a, &b).unwrap();
+        assert_eq!(a * x + b, r(0, 1));
+    }
+
+    #[test]
+    fn convergent_errors_alternate_and_shrink() {
+        for r in [
+            Rational::new(415, 93),
+            Rational::new(355, 113),
+            Rational::new(-103993, 33102),
+            Rational::new(1, 7),
+            Rational::new(884279719, 281474976),
+        ] {
+            let errors = r.convergent_errors().unwrap();
+            assert_eq!(errors.len(), r.to_continued_fraction().len());
+            assert_parts(*errors.last().unwrap(), 0, 1);
+            let nonzero = &errors[..errors.len() - 1];
+            for w in nonzero.windows(2) {
+                assert!(w[0].numer.signum() == -w[1].numer.signum(), "{:?}", errors);
+                assert!(w[1].abs_diff(&Rational::new(0, 1)) < w[0].abs_diff(&Rational::new(0, 1)));
+            }
+        }
+        let e = Rational::new(415, 93).convergent_errors().unwrap();
+        assert_parts(e[0], -43, 93);
+        assert_parts(Rational::new(5, 1).convergent_errors().unwrap()[0], 0, 1);
+        assert_eq!(
+            Rational::from_f64_exact(std::f64::consts::PI).unwrap().convergent_errors(),
+            Err(RationalError::DenominatorOverflow)
+        );
+    }
+
+    #[test]
+    fn golden_ratio_convergents_are_fibonacci_ratios() {
+        let c = Rational::golden_ratio_convergents(6);
+        assert_eq!(pairs(&c), vec![(1, 1), (2, 1), (3, 2), (5, 3), (8, 5), (13, 8)]);
+        assert!(Rational::golden_ratio_convergents(0).is_empty());
+        let all = Rational::golden_ratio_convergents(91);
+        assert!(all.windows(2).all(|w| w[0].are_farey_neighbors(&w[1])));
+        assert_eq!(all[89], Rational::from_continued_fraction(&[1; 90]));
+        let phi = (1.0 + 5f64.sqrt()) / 2.0;
+        assert!((all[90].value() - phi).abs() < 1e-15);
+    }
+
+    #[test]
+    #[should_panic]
+    fn golden_ratio_convergents_panic_past_91() {
+        Rational::golden_ratio_convergents(92);
+    }
+
+    #[test]
+    fn barycentric_inverts_lerp() {
+        let r = |n, d| Rational::new(n, d);
+        assert_parts(Rational::barycentric(&r(1, 2), &r(1, 3), &r(2, 3)).unwrap(), 1, 2);
+        assert_parts(Rational::barycentric(&r(1, 3), &r(1, 3), &r(2, 3)).unwrap(), 0, 1);
+        assert_parts(Rational::barycentric(&r(2, 3), &r(1, 3), &r(2, 3)).unwrap(), 1, 1);
+        assert_parts(
+            Rational::barycentric(&r(0, 1), &r(1, 3), &r(2, 3)).unwrap(),
+            -1,
+            1,
+        );
+        assert_parts(
+            Rational::barycentric(&r(1, 1), &r(3, 1), &r(-1, 1)).unwrap(),
+            1,
+            2,
+        );
+        assert!(Rational::barycentric(&r(1, 2), &r(2, 4), &r(1, 2)).is_none());
+        let (p, s, e) = (r(7, 11), r(-2, 5), r(9, 4));
+        let t = Rational::barycentric(&p, &s, &e).unwrap();
+        assert_eq!(s + t * (e - s), p);
+    }
+
+    #[test]
+    fn stern_brocot_search_steps_like_from() {
+        let mut g = Lcg(176);
+        for _ in 0..300 {
+            let v = (g.next() % 1000000) as f64 / 1237.0;
+            let e = 1.0 / (1 + g.next() % 100000) as f64;
+            let mut search = SternBrocotSearch::new(v, e);
+            let mut steps = 0u64;
+            let mut last_continue = None;
+            let result = loop {
+                steps += 1;
+                match search.step() {
+                    SearchStatus::Continue(m) => last_continue = Some(m),
+                    SearchStatus::Done(r) => break r,
+                }
+            };
+            let (f, s) = Rational::from_with_stats(v, e);
+            assert_eq!((result.numer, result.denom), (f.numer, f.denom));
+            assert_eq!(s, steps);
+            if let Some(m) = last_continue {
+                assert!((m.value() - v).abs() > e);
+            }
+            assert_eq!(search.step(), SearchStatus::Done(result));
+        }
+        let mut s = SternBrocotSearch::new(0.4, 0.01);
+        assert_eq!(s.step(), SearchStatus::Continue(Rational::new(1, 1)));
+        assert_eq!(s.step(), SearchStatus::Continue(Rational::new(1, 2)));
+        assert_eq!(s.step(), SearchStatus::Continue(Rational::new(1, 3)));
+        assert_eq!(s.step(), SearchStatus::Done(Rational::new(2, 5)));
+    }
+
+    #[test]
+    fn and_or_combine_independent_probabilities() {
+        let half = Rational::new(1, 2);
+        assert_parts(half.and(&half).unwrap(), 1, 4);
+        assert_parts(half.or(&half).unwrap(), 3, 4);
+        let (a, b) = (Rational::new(2, 7), Rational::new(3, 5));
+        assert_eq!(a.or(&b).unwrap(), a + b - a * b);
+        assert_parts(Rational::new(0, 1).or(&b).unwrap(), 3, 5);
+        assert_parts(Rational::new(1, 1).or(&b).unwrap(), 1, 1);
+        assert_parts(Rational::new(0, 1).and(&b).unwrap(), 0, 1);
+        assert_parts(Rational::new(1, 1).and(&b).unwrap(), 3, 5);
+        assert_eq!(Rational::new(3, 2).and(&half), Err(RationalError::OutOfRange));
+        assert_eq!(half.or(&Rational::new(-1, 3)), Err(RationalError::OutOfRange));
+        let big = Rational::new(i64::MAX - 1, i64::MAX);
+        assert!(big.and(&Rational::new(1, i64::MAX - 2)).is_err());
+    }
+
+    #[test]
+    fn median_sorts_in_place() {
+        let r = |n, d| Rational::new(n, d);
+        let mut odd = [r(3, 4), r(-1, 2), r(1, 3), r(5, 1), r(1, 2)];
+        assert_parts(Rational::median(&mut odd).unwrap(), 1, 2);
+        assert_eq!(pairs(&odd), vec![(-1, 2), (1, 3), (1, 2), (3, 4), (5, 1)]);
+        let mut even = [r(1, 3), r(1, 2), r(-7, 1), r(9, 1)];
+        assert_parts(Rational::median(&mut even).unwrap(), 5, 12);
+        assert!(Rational::median(&mut []).is_none());
+        assert_parts(Rational::median(&mut [r(2, 9)]).unwrap(), 2, 9);
+        let mut big = [r(i64::MAX, 1), r(i64::MAX - 2, 1)];
+        assert_parts(Rational::median(&mut big).unwrap(), i64::MAX - 1, 1);
+    }
+
+    #[test]
+    fn binary_expansion_of_the_fractional_part() {
+        assert_eq!(Rational::new(1, 2).binary_expansion(10), (vec![true], true));
+        let (bits, done) = Rational::new(1, 3).binary_expansion(6);
+        assert_eq!(bits, vec![false, true, false, true, false, true]);
+        assert!(!done);
+        assert_eq!(Rational::new(0, 1).binary_expansion(5), (vec![], true));
+        assert_eq!(
+            Rational::new(5, 8).binary_expansion(3),
+            (vec![true, false, true], true)
+        );
+        assert_eq!(
+            Rational::new(5, 8).binary_expansion(2),
+            (vec![true, false], false)
+        );
+        assert_eq!(
+            Rational::new(-3, 4).binary_expansion(8),
+            (vec![false, true], true)
+        );
+        let (bits, done) = Rational::new(i64::MAX - 1, i64::MAX).binary_expansion(64);
+        assert_eq!(bits.len(), 64);
+        assert!(!done);
+    }
+
+    #[test]
+    fn mobius_applies_the_transform() {
+        let x = Rational::new(-5, 7);
+        assert_parts(x.mobius(1, 0, 0, 1).unwrap(), -5, 7);
+        assert_parts(x.mobius(0, 1, 1, 0).unwrap(), -7, 5);
+        assert!(Rational::new(3, 1).mobius(2, 1, 1, -3).is_none());
+        assert!(Rational::new(0, 1).mobius(0, 1, 1, 0).is_none());
+        assert_parts(Rational::new(1, 2).mobius(2, 1, 3, 4).unwrap(), 4, 11);
+        assert_parts(Rational::new(i64::MAX, 1).mobius(1, 0, 1, 0).unwrap(), 1, 1);
+    }
+
+    #[test]
+    fn terminates_in_base_checks_prime_factors() {
+        assert!(Rational::new(1, 3).terminates_in_base(3));
+        assert!(!Rational::new(1, 3).terminates_in_base(10));
+        assert!(Rational::new(1, 8).terminates_in_base(2));
+        assert!(Rational::new(1, 8).terminates_in_base(10));
+        assert!(Rational::new(7, 1).terminates_in_base(7));
+        assert!(Rational::new(1, 12).terminates_in_base(6));
+        assert!(!Rational::new(1, 12).terminates_in_base(4));
+        assert!(Rational::new(-1, 1 << 62).terminates_in_base(2));
+        assert_eq!(
+            Rational::new(3, 40).terminates_in_base(10),
+            Rational::new(3, 40).decimal_period() == 0
+        );
+    }
+
+    #[test]
+    fn cross_ratio_is_mobius_invariant() {
+        let r = |n, d| Rational::new(n, d);
+        assert_parts(
+            Rational::cross_ratio(&r(0, 1), &r(1, 1), &r(2, 1), &r(3, 1)).unwrap(),
+            4,
+            3,
+        );
+        assert_parts(
+            Rational::cross_ratio(&r(1, 2), &r(1, 3), &r(1, 4), &r(1, 5)).unwrap(),
+            4,
+            3,
+        );
+        let (a, b, c, d) = (r(-2, 3), r(5, 7), r(1, 9), r(4, 1));
+        let expected = ((a - c) * (b - d)) / ((a - d) * (b - c));
+        assert_eq!(Rational::cross_ratio(&a, &b, &c, &d).unwrap(), expected);
+        let t = |x: Rational| x.mobius(2, 1, 1, 3).unwrap();
+        assert_eq!(
+            Rational::cross_ratio(&t(a), &t(b), &t(c), &t(d)).unwrap(),
+            expected
+        );
+        assert!(Rational::cross_ratio(&r(1, 1), &r(2, 1), &r(3, 1), &r(1, 1)).is_none());
+        assert!(Rational::cross_ratio(&r(1, 1), &r(2, 1), &r(2, 1), &r(5, 1)).is_none());
+        assert_parts(
+            Rational::cross_ratio(&r(1, 1), &r(2, 1), &r(1, 1), &r(5, 1)).unwrap(),
+            0,
+            1,
+        );
+    }
+
+    #[test]
+    fn from_binary_fraction_reads_repeating_bits() {
+        assert_parts(Rational::from_binary_fraction("0", "01").unwrap(), 1, 3);
+        assert_parts(Rational::from_binary_fraction("0.1", "").unwrap(), 1, 2);
+        assert_parts(Rational::from_binary_fraction("0.1", "10").unwrap(), 5, 6);
+        assert_parts(Rational::from_binary_fraction("101", "").unwrap(), 5, 1);
+        assert_parts(Rational::from_binary_fraction("1.01", "").unwrap(), 5, 4);
+        assert_parts(Rational::from_binary_fraction("0", "1").unwrap(), 1, 1);
+        assert_parts(Rational::from_binary_fraction("0", "001").unwrap(), 1, 7);
+        assert_eq!(
+            Rational::from_binary_fraction("", "01"),
+            Err(RationalError::InvalidFormat)
+        );
+        assert_eq!(
+            Rational::from_binary_fraction("0.2", ""),
+            Err(RationalError::InvalidFormat)
+        );
+        assert_eq!(
+            Rational::from_binary_fraction("0", "0a"),
+            Err(RationalError::InvalidFormat)
+        );
+        assert_eq!(
+            Rational::from_binary_fraction("-1", ""),
+            Err(RationalError::InvalidFormat)
+        );
+        assert_eq!(
+            Rational::from_binary_fraction("+1", ""),
+            Err(RationalError::InvalidFormat)
+        );
+        assert_eq!(
+            Rational::from_binary_fraction("0.1.1", ""),
+            Err(RationalError::InvalidFormat)
+        );
+        assert!(matches!(
+            Rational::from_binary_fraction(&"1".repeat(64), ""),
+            Err(RationalError::Overflow(_))
+        ));
+        assert!(matches!(
+            Rational::from_binary_fraction("0", &"01".repeat(32)),
+            Err(RationalError::Overflow(_))
+        ));
+        for (n, d) in [(1, 2), (5, 8), (3, 1024), (0, 1), (1023, 1024), (7, 1 << 40)] {
+            let r = Rational::new(n, d);
+            let (bits, done) = r.binary_expansion(64);
+            assert!(done);
+            let s: String = bits.iter().map(|&b| if b { '1' } else { '0' }).collect();
+            assert_eq!(
+                Rational::from_binary_fraction(&format!("0.{}", s), "").unwrap(),
+                r
+            );
+        }
+        let (bits, done) = Rational::new(1, 3).binary_expansion(2);
+        assert!(!done);
+        let s: String = bits.iter().map(|&b| if b { '1' } else { '0' }).collect();
+        assert_parts(Rational::from_binary_fraction("0", &s).unwrap(), 1, 3);
+    }
+
+    #[test]
+    fn decompose_over_prime_powers_sums_back() {
+        let parts = Rational::new(1, 6).decompose_over_prime_powers();
+        assert_eq!(pairs(&parts), vec![(1, 2), (-1, 3)]);
+        assert_eq!(parts.iter().copied().sum::<Rational>(), Rational::new(1, 6));
+        for (n, d) in [
+            (7, 360),
+            (-5, 84),
+            (1, 97),
+            (3, 8),
+            (11, 1001),
+            (2, 1),
+            (0, 1),
+            (1_000_003, 2 * 3 * 5 * 7 * 11 * 13 * 17 * 19 * 23),
+            (-2, 9223372036854775783),
+            (12345, 2 * 4611686014132420609),
+            (1, 1 << 62),
+        ] {
+            let r = Rational::new(n, d);
+            let parts = r.decompose_over_prime_powers();
+            assert_eq!(parts.iter().copied().sum::<Rational>(), r, "{:?}", parts);
+            for w in parts.windows(2) {
+                assert_eq!(Rational::gcd(w[0].denom, w[1].denom), 1);
+            }
+        }
+        assert_eq!(
+            Rational::new(7, 360)
+                .decompose_over_prime_powers()
+                .iter()
+                .map(|r| r.denom)
+                .collect::<Vec<_>>(),
+            vec![8, 9, 5]
+        );
+    }
+
+    #[test]
+    fn steps_to_reach_counts_from_steps() {
+        let mut g = Lcg(185);
+        for _ in 0..300 {
+            let v = (g.next() % 1000000) as f64 / 1237.0;
+            let e = 1.0 / (1 + g.next() % 100000) as f64;
+            assert_eq!(
+                Rational::steps_to_reach(v, e),
+                Rational::from_with_stats(v, e).1,
+                "{} {}",
+                v,
+                e
+            );
+        }
+        let pi = std::f64::consts::PI;
+        let mut previous = 0;
+        for e in [1.0, 0.1, 1e-2, 1e-3, 1e-4, 1e-5, 1e-6, 1e-7, 1e-9, 1e-12] {
+            let s = Rational::steps_to_reach(pi, e);
+            assert!(s >= previous);
+            previous = s;
+        }
+        assert_eq!(
+            Rational::steps_to_reach(pi, 1e-6),
+            Rational::from_with_stats(pi, 1e-6).1
+        );
+        assert!(Rational::steps_to_reach(6e-14, 1e-30) > 1_000_000_000_000);
+        assert_eq!(Rational::steps_to_reach(1.0, 0.1), 1);
+    }
+
+    #[test]
+    fn add_via_continued_fraction_matches_add() {
+        let r = |n, d| Rational::new(n, d);
+        assert_parts(r(1, 2).add_via_continued_fraction(&r(1, 3)), 5, 6);
+        assert_parts(r(-1, 2).add_via_continued_fraction(&r(1, 2)), 0, 1);
+        assert_parts(r(-7, 3).add_via_continued_fraction(&r(-2, 9)), -23, 9);
+        assert_parts(r(3, 1).add_via_continued_fraction(&r(4, 1)), 7, 1);
+        assert_parts(r(355, 113).add_via_continued_fraction(&r(-22, 7)), -1, 791);
+        let mut g = Lcg(186);
+        for i in 0..20000u32 {
+            let lim = if i.is_multiple_of(2) { 50 } else { 1_000_000_000 };
+            let a = r(g.next() as i64 % lim - lim / 2, 1 + g.next() as i64 % lim);
+            let b = r(g.next() as i64 % lim - lim / 2, 1 + g.next() as i64 % lim);
+            assert_eq!(a.add_via_continued_fraction(&b), a + b, "{:?} {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn iterate_map_matches_the_closed_form() {
+        let r = |n, d| Rational::new(n, d);
+        let (a, b, x0) = (r(1, 2), r(1, 2), r(0, 1));
+        let mut previous = x0;
+        for n in 1..=20u32 {
+            let x = x0.iterate_map(&a, &b, n).unwrap();
+            assert_parts(x, (1 << n) - 1, 1 << n);
+            assert!(x > previous && x < r(1, 1));
+            previous = x;
+        }
+        assert_parts(x0.iterate_map(&a, &b, 0).unwrap(), 0, 1);
+        let (a, b, x0) = (r(2, 3), r(-1, 5), r(7, 4));
+        let fixed = b / (r(1, 1) - a);
+        for n in 0..6u32 {
+            let closed = fixed + r(2i64.pow(n), 3i64.pow(n)) * (x0 - fixed);
+            assert_eq!(x0.iterate_map(&a, &b, n).unwrap(), closed);
+        }
+        assert_eq!(
+            r(1, 1).iterate_map(&r(3, 1), &r(0, 1), 50),
+            Err(RationalError::Overflow(OverflowDirection::Positive))
+        );
+        assert_eq!(
+            r(-1, 1).iterate_map(&r(3, 1), &r(0, 1), 50),
+            Err(RationalError::Overflow(OverflowDirection::Negative))
+        );
+        assert_parts(r(i64::MAX, 3).checked_mul(&r(3, i64::MAX)).unwrap(), 1, 1);
+        assert_parts(r(i64::MAX, 1).checked_add(&r(-1, 1)).unwrap(), i64::MAX - 1, 1);
+        assert!(r(i64::MAX, 1).checked_add(&r(1, 1)).is_err());
+    }
+
+    #[test]
+    fn snap_to_farey_matches_brute_force() {
+        let r = |n, d| Rational::new(n, d);
+        assert_parts(r(2, 5).snap_to_farey(3), 1, 3);
+        assert_parts(Rational::from_f64_exact(0.4).unwrap().snap_to_farey(3), 1, 3);
+        assert_parts(r(9, 20).snap_to_farey(3), 1, 2);
+        assert_parts(r(5, 12).snap_to_farey(3), 1, 2);
+        assert_parts(r(1, 6).snap_to_farey(3), 0, 1);
+        assert_parts(r(1, 6).snap_to_farey(6), 1, 6);
+        assert_parts(r(-3, 2).snap_to_farey(5), 0, 1);
+        assert_parts(r(7, 3).snap_to_farey(5), 1, 1);
+        assert_parts(r(1, 1).snap_to_farey(1), 1, 1);
+        assert_parts(r(2, 5).snap_to_farey(1), 0, 1);
+        assert_parts(r(3, 5).snap_to_farey(1), 1, 1);
+        for order in 1..25 {
+            for d in 1..60 {
+                for n in 0..=d {
+                    let x = r(n, d);
+                    let s = x.snap_to_farey(order);
+                    let mut best: Option<Rational> = None;
+                    for q in 1..=order {
+                        for p in 0..=q {
+                            let c = r(p, q);
+                            if best.is_none_or(|b| {
+                                c.abs_diff(&x) < b.abs_diff(&x)
+                                    || (c.abs_diff(&x) == b.abs_diff(&x) && c.denom < b.denom)
+                            }) {
+                                best = Some(c);
+                            }
+                        }
+                    }
+                    assert_eq!(s, best.unwrap(), "{:?} {}", x, order);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fast_from_agrees_with_from() {
+        let pi = std::f64::consts::PI;
+        Rational::assert_algorithms_agree(pi, 1e-7);
+        Rational::assert_algorithms_agree(pi, 1e-6);
+        let mut g = Lcg(189);
+        for i in 0..20000 {
+            let v = match i % 3 {
+                0 => (g.next() % 1_000_000) as f64 / 997.0,
+                1 => (g.next() % 1_000_000_000) as f64 * 1e-9,
+                _ => 1.0 + (g.next() % 4096) as f64 / 4096.0,
+            };
+            let e = 10f64.powi(-((g.next() % 9) as i32)) * (1 + g.next() % 9) as f64;
+            if Rational::steps_to_reach(v, e) > 2_000_000 {
+                continue;
+            }
+            Rational::assert_algorithms_agree(v, e);
+        }
+        // boundary cases: value exactly one error bound away from a simple fraction
+        for (n, d) in [(1, 3), (2, 7), (22, 7), (355, 113), (5, 1)] {
+            for e in [0.5, 0.1, 1e-3, 1e-5] {
+                let x = n as f64 / d as f64;
+                for v in [x + e, x - e] {
+                    if v > 0.0 && Rational::steps_to_reach(v, e) < 2_000_000 {
+                        Rational::assert_algorithms_agree(v, e);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_interval_sum_pairs_heights_and_widths() {
+        let r = |n, d| Rational::new(n, d);
+        let heights = [r(1, 2), r(3, 1), r(-2, 3)];
+        let widths = [r(1, 1), r(1, 4), r(3, 2)];
+        assert_parts(Rational::weighted_interval_sum(&heights, &widths).unwrap(), 1, 4);
+        assert!(Rational::weighted_interval_sum(&heights, &widths[..2]).is_none());
+        assert_parts(Rational::weighted_interval_sum(&[], &[]).unwrap(), 0, 1);
+    }
+
+    #[test]
+    fn relative_error_scales_with_the_value() {
+        let rel = |r: Rational, v: f64| ((r.value() - v) / v).abs();
+        for (v, e) in [
+            (12_581_890_123.53, 1e-12),
+            (12_581_890_123.53, 1e-6),
+            (std::f64::consts::PI, 1e-9),
+            (6.02214076e23 / 1e10, 1e-8),
+            (1.602e-9, 1e-6),
+            (6e-14, 1e-3),
+            (-std::f64::consts::E, 1e-10),
+            (0.3333, 1e-2),
+        ] {
+            let r = Rational::from_relative_error(v, e);
+            assert!(rel(r, v) <= e, "{} {} {:?}", v, e, r);
+        }
+        assert_parts(Rational::from_relative_error(0.0, 1e-3), 0, 1);
+        assert_parts(Rational::from_relative_error(std::f64::consts::PI, 1e-3), 22, 7);
+        assert_parts(Rational::from_relative_error(-std::f64::consts::PI, 1e-3), -22, 7);
+        assert_parts(Rational::from_relative_error(1e-9, 1e-6), 1, 999_999_001);
+        assert_parts(Rational::from_relative_error(0.25, 0.0), 1, 4);
+        let small = Rational::from_relative_error(1.602e-9, 1e-6);
+        assert!(small.denom > 100_000_000);
+        assert_eq!(
+            Rational::from_significant_digits(std::f64::consts::PI, 3),
+            Rational::from_relative_error(std::f64::consts::PI, 1e-3)
+        );
+        let mut g = Lcg(191);
+        for _ in 0..2000 {
+            let v = (g.next() % 1_000_000) as f64 / 991.0 + 0.5;
+            let e = 10f64.powi(-((g.next() % 8) as i32));
+            let (sd, fr) = (Rational::from(v, v * e), Rational::from_relative_error(v, e));
+            assert_eq!((sd.numer, sd.denom), (fr.numer, fr.denom));
+        }
+    }
+}